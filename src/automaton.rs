@@ -0,0 +1,322 @@
+use crate::grid::{Grid, Point};
+
+/// The cells a rule looks at around each cell, always in the same order.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Neighbourhood {
+    /// The 4 orthogonal neighbours: up, left, right and down.
+    VonNeumann,
+    /// The 8 surrounding cells in reading order.
+    Moore,
+    /// The 3x3 window centred on the cell, itself included, in reading order.
+    Window,
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighbourhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Neighbourhood::Window => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (0, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+/// A chain reaction within a single step. Every cell is charged first, then each cell
+/// that fires changes state and affects its neighbours, which may fire in turn. `fire`
+/// has to leave the cell in a state that doesn't fire, so it only fires once.
+pub struct Cascade<T> {
+    pub charge: Box<dyn Fn(&T) -> T>,
+    pub fires: Box<dyn Fn(&T) -> bool>,
+    pub fire: Box<dyn Fn(&T) -> T>,
+    /// What a neighbour of a firing cell becomes.
+    pub propagate: Box<dyn Fn(&T) -> T>,
+}
+
+/// The next state of a cell from its current one and its neighbours.
+pub type Transition<T> = Box<dyn Fn(&T, &[T]) -> T>;
+
+pub enum Update<T> {
+    /// Every cell takes its next state at once.
+    Synchronous(Transition<T>),
+    Cascading(Cascade<T>),
+}
+
+pub struct Rule<T> {
+    pub neighbourhood: Neighbourhood,
+    pub update: Update<T>,
+}
+
+/// A Life-like rule such as `B3/S23`: a dead cell with a number of live neighbours
+/// listed after `B` is born, a live one with a number listed after `S` survives.
+pub fn life(rule: &str) -> Option<Rule<bool>> {
+    let (born, survives) = rule.split_once('/')?;
+
+    let counts = |digits: &str| {
+        digits.chars().try_fold([false; 9], |mut counts, c| {
+            *counts.get_mut(c.to_digit(10)? as usize)? = true;
+            Some(counts)
+        })
+    };
+    let born = counts(born.strip_prefix('B')?)?;
+    let survives = counts(survives.strip_prefix('S')?)?;
+
+    Some(Rule {
+        neighbourhood: Neighbourhood::Moore,
+        update: Update::Synchronous(Box::new(move |&alive, neighbours| {
+            let count = neighbours.iter().filter(|&&n| n).count();
+
+            if alive {
+                survives[count]
+            } else {
+                born[count]
+            }
+        })),
+    })
+}
+
+/// A grid of cells, optionally floating on an infinite background of identical cells.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Automaton<T> {
+    pub cells: Grid<T>,
+    /// The state of every cell outside the grid, `None` when there are none.
+    pub background: Option<T>,
+    /// Adds a ring of background cells around the grid before every step, the only
+    /// ones a rule looking at direct neighbours can change.
+    pub grows: bool,
+}
+
+impl<T: Clone> Automaton<T> {
+    pub fn new(cells: Grid<T>) -> Self {
+        Automaton {
+            cells,
+            background: None,
+            grows: false,
+        }
+    }
+
+    /// Surrounds the grid with `background` cells, growing it at every step.
+    pub fn unbounded(cells: Grid<T>, background: T) -> Self {
+        Automaton {
+            cells,
+            background: Some(background),
+            grows: true,
+        }
+    }
+
+    pub fn step(&mut self, rule: &Rule<T>) {
+        let offsets = rule.neighbourhood.offsets();
+
+        match &rule.update {
+            Update::Synchronous(next) => self.step_synchronous(offsets, next),
+            Update::Cascading(cascade) => self.step_cascading(offsets, cascade),
+        }
+    }
+
+    /// The state at `p` moved by `(dx, dy)`, `None` outside the grid without a background.
+    fn cell(&self, p: (isize, isize), dx: isize, dy: isize) -> Option<T> {
+        let (x, y) = (p.0 + dx, p.1 + dy);
+
+        if x < 0 || y < 0 {
+            return self.background.clone();
+        }
+
+        self.cells
+            .get(Point::new(x as usize, y as usize))
+            .cloned()
+            .or_else(|| self.background.clone())
+    }
+
+    /// Neighbours outside the grid are the background, or left out without one.
+    fn step_synchronous(&mut self, offsets: &[(isize, isize)], next: &dyn Fn(&T, &[T]) -> T) {
+        let ring = self.grows as usize;
+        let mut neighbours = Vec::with_capacity(offsets.len());
+
+        let cells = Grid::from_fn(
+            self.cells.width() + 2 * ring,
+            self.cells.height() + 2 * ring,
+            |p| {
+                let old = (p.x as isize - ring as isize, p.y as isize - ring as isize);
+
+                neighbours.clear();
+                neighbours.extend(
+                    offsets
+                        .iter()
+                        .filter_map(|&(dx, dy)| self.cell(old, dx, dy)),
+                );
+
+                let cell = self.cell(old, 0, 0).unwrap();
+                next(&cell, &neighbours)
+            },
+        );
+
+        self.background = self.background.take().map(|background| {
+            let neighbours = vec![background.clone(); offsets.len()];
+            next(&background, &neighbours)
+        });
+        self.cells = cells;
+    }
+
+    /// Only cells in the grid take part, the background never changes.
+    fn step_cascading(&mut self, offsets: &[(isize, isize)], cascade: &Cascade<T>) {
+        let mut firing = Vec::new();
+
+        for (p, cell) in self.cells.iter_mut() {
+            *cell = (cascade.charge)(cell);
+
+            if (cascade.fires)(cell) {
+                firing.push(p);
+            }
+        }
+
+        while let Some(p) = firing.pop() {
+            if !(cascade.fires)(&self.cells[p]) {
+                continue;
+            }
+
+            self.cells[p] = (cascade.fire)(&self.cells[p]);
+
+            for &(dx, dy) in offsets {
+                if let Some(q) = self.cells.offset(p, dx, dy) {
+                    self.cells[q] = (cascade.propagate)(&self.cells[q]);
+
+                    if (cascade.fires)(&self.cells[q]) {
+                        firing.push(q);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Automaton<T> {
+    /// Crops the grid to the cells that differ from the background, the same picture
+    /// wherever it drifted to.
+    pub fn trimmed(&self) -> Self {
+        let Some(background) = &self.background else {
+            return self.clone();
+        };
+
+        let (mut from, mut to) = (Point::new(usize::MAX, usize::MAX), Point::new(0, 0));
+        for (p, _) in self.cells.iter().filter(|(_, c)| *c != background) {
+            from = Point::new(from.x.min(p.x), from.y.min(p.y));
+            to = Point::new(to.x.max(p.x + 1), to.y.max(p.y + 1));
+        }
+
+        let (width, height) = (to.x.saturating_sub(from.x), to.y.saturating_sub(from.y));
+        Automaton {
+            cells: Grid::from_fn(width, height, |p| {
+                self.cells[Point::new(from.x + p.x, from.y + p.y)].clone()
+            }),
+            background: self.background.clone(),
+            grows: self.grows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Source;
+
+    fn cells(picture: &str) -> Grid<bool> {
+        Grid::parse_with(&Source::new(0, picture), picture, |c| Some(c == '#')).unwrap()
+    }
+
+    fn alive(automaton: &Automaton<bool>) -> usize {
+        automaton.cells.cells().iter().filter(|&&c| c).count()
+    }
+
+    #[test]
+    fn test_life_rules() {
+        assert!(life("B3/S23").is_some());
+        assert!(life("B36/S").is_some());
+        assert!(life("B9/S23").is_none());
+        assert!(life("3/23").is_none());
+        assert!(life("B3S23").is_none());
+    }
+
+    #[test]
+    fn test_blinker() {
+        let rule = life("B3/S23").unwrap();
+        let start = cells(".....\n..#..\n..#..\n..#..\n.....");
+        let mut automaton = Automaton::new(start.clone());
+
+        automaton.step(&rule);
+        assert_eq!(automaton.cells, cells(".....\n.....\n.###.\n.....\n....."));
+
+        automaton.step(&rule);
+        assert_eq!(automaton.cells, start);
+    }
+
+    #[test]
+    fn test_glider() {
+        let rule = life("B3/S23").unwrap();
+        let mut automaton = Automaton::unbounded(cells(".#.\n..#\n###"), false);
+
+        for _ in 0..8 {
+            automaton.step(&rule);
+            assert_eq!(alive(&automaton), 5);
+        }
+
+        // Two full periods later the glider moved two cells down and right, the grid
+        // grew by a ring on every step.
+        let region = automaton.cells.region(Point::new(10, 10), 3, 3);
+        assert_eq!(
+            region.map(|(_, &c)| c).collect::<Vec<_>>(),
+            cells(".#.\n..#\n###").cells()
+        );
+        assert_eq!(automaton.cells.width(), 19);
+        assert_eq!(
+            automaton.trimmed(),
+            Automaton::unbounded(cells(".#.\n..#\n###"), false)
+        );
+    }
+
+    #[test]
+    fn test_background() {
+        // Every dead cell is born, every live one dies.
+        let rule = life("B012345678/S").unwrap();
+        let mut automaton = Automaton::unbounded(cells("#"), false);
+
+        automaton.step(&rule);
+        assert_eq!(automaton.background, Some(true));
+        assert_eq!(automaton.cells, cells("###\n#.#\n###"));
+    }
+
+    #[test]
+    fn test_cascade() {
+        // Cells above 2 fire once, ending up at -1 and adding 1 to their neighbours.
+        let rule = Rule {
+            neighbourhood: Neighbourhood::VonNeumann,
+            update: Update::Cascading(Cascade {
+                charge: Box::new(|&n| n),
+                fires: Box::new(|&n: &i32| n > 2),
+                fire: Box::new(|_| -1),
+                propagate: Box::new(|&n| if n < 0 { n } else { n + 1 }),
+            }),
+        };
+        let mut automaton = Automaton::new(Grid::new(4, 1, vec![3, 2, 2, 1]));
+
+        automaton.step(&rule);
+        assert_eq!(automaton.cells.cells(), &[-1, -1, -1, 2]);
+    }
+}
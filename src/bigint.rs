@@ -0,0 +1,148 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul};
+
+/// An arbitrary precision unsigned integer, stored as little endian 32 bit limbs
+/// without trailing zeros.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Default::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalized(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        BigUint { limbs }
+    }
+
+    /// Divides in place by a small divisor, returning the remainder.
+    fn div_rem_small(&mut self, divisor: u64) -> u64 {
+        assert_ne!(divisor, 0, "division by zero");
+
+        let mut remainder = 0u128;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = remainder << 32 | *limb as u128;
+            *limb = (current / divisor as u128) as u32;
+            remainder = current % divisor as u128;
+        }
+
+        *self = Self::normalized(std::mem::take(&mut self.limbs));
+
+        remainder as u64
+    }
+
+    pub fn rem(&self, modulus: u64) -> u64 {
+        self.clone().div_rem_small(modulus)
+    }
+
+    /// The value if it fits in a `u64`.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.limbs.as_slice() {
+            [] => Some(0),
+            [low] => Some(*low as u64),
+            [low, high] => Some((*high as u64) << 32 | *low as u64),
+            _ => None,
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        Self::normalized(vec![n as u32, (n >> 32) as u32])
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(rhs.limbs.len()) + 1);
+        let mut carry = 0u64;
+
+        for i in 0..self.limbs.len().max(rhs.limbs.len()) {
+            let sum = carry
+                + self.limbs.get(i).cloned().unwrap_or_default() as u64
+                + rhs.limbs.get(i).cloned().unwrap_or_default() as u64;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut limbs = vec![0u32; self.limbs.len() + rhs.limbs.len()];
+
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+
+            for (j, &b) in rhs.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+
+            limbs[i + rhs.limbs.len()] = carry as u32;
+        }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Display for BigUint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        const CHUNK: u64 = 1_000_000_000;
+
+        let mut rest = self.clone();
+        let mut chunks = vec![rest.div_rem_small(CHUNK)];
+        while !rest.is_zero() {
+            chunks.push(rest.div_rem_small(CHUNK));
+        }
+
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let a = BigUint::from(u64::MAX);
+        let b = BigUint::from(12345678901234567890);
+
+        assert_eq!((&a + &b).to_string(), "30792422974944119505");
+        assert_eq!(
+            (&a * &b).to_string(),
+            "227737579107269814010216029110176642350"
+        );
+        assert_eq!((&a * &BigUint::zero()), BigUint::zero());
+        assert_eq!((&a * &b).rem(1_000_000_007), 896_238_090);
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from(1_000_000_000).to_string(), "1000000000");
+        assert_eq!((&a + &b).to_u64(), None);
+        assert_eq!(b.to_u64(), Some(12345678901234567890));
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// The state after `offset + period` steps is the same as the one after `offset`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cycle {
+    pub offset: usize,
+    pub period: usize,
+}
+
+/// The states a deterministic simulation went through, up to its first repeat.
+pub struct History<S> {
+    /// The state after each step, the starting one first.
+    states: Vec<S>,
+    cycle: Option<Cycle>,
+}
+
+impl<S: Eq + Hash> History<S> {
+    /// Steps from `start` until a state comes back, giving up after `limit` steps.
+    pub fn record(start: S, mut step: impl FnMut(&S) -> S, limit: usize) -> Self {
+        let hash = |state: &S| {
+            let mut hasher = DefaultHasher::new();
+            state.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // Only the hashes are kept here, so each state is stored once.
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        seen.entry(hash(&start)).or_default().push(0);
+        let mut states = vec![start];
+
+        for i in 1..=limit {
+            let next = step(&states[i - 1]);
+            let indices = seen.entry(hash(&next)).or_default();
+
+            if let Some(&offset) = indices.iter().find(|&&j| states[j] == next) {
+                return History {
+                    states,
+                    cycle: Some(Cycle {
+                        offset,
+                        period: i - offset,
+                    }),
+                };
+            }
+
+            indices.push(i);
+            states.push(next);
+        }
+
+        History {
+            states,
+            cycle: None,
+        }
+    }
+}
+
+/// The state after `steps` steps. A cycle among the first `limit` steps is used to skip
+/// ahead, without one the remaining steps are simulated.
+pub fn state_after<S: Clone + Eq + Hash>(
+    start: S,
+    mut step: impl FnMut(&S) -> S,
+    steps: u64,
+    limit: usize,
+) -> S {
+    let history = History::record(start, &mut step, steps.min(limit as u64) as usize);
+
+    if let Some(state) = history.state(steps) {
+        return state.clone();
+    }
+
+    let recorded = history.states.len() as u64 - 1;
+    let mut state = history.states.last().unwrap().clone();
+    for _ in recorded..steps {
+        state = step(&state);
+    }
+
+    state
+}
+
+/// The first step after which `found` holds, `None` once the states repeat without it.
+/// Repeats are found with Brent's algorithm, which only keeps two states around however
+/// many steps it takes.
+pub fn find_step<S: Clone + Eq>(
+    start: S,
+    mut step: impl FnMut(&S) -> S,
+    mut found: impl FnMut(&S) -> bool,
+) -> Option<u64> {
+    let mut saved = start.clone();
+    let mut state = start;
+    let (mut steps, mut power, mut since_saved) = (0, 1, 0);
+
+    loop {
+        state = step(&state);
+        steps += 1;
+
+        if found(&state) {
+            return Some(steps);
+        }
+
+        if state == saved {
+            return None;
+        }
+
+        since_saved += 1;
+        if since_saved == power {
+            saved = state.clone();
+            power *= 2;
+            since_saved = 0;
+        }
+    }
+}
+
+impl<S> History<S> {
+    /// Every distinct state in order, the starting one first.
+    pub fn states(&self) -> &[S] {
+        &self.states
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    fn index(&self, steps: u64) -> Option<usize> {
+        if steps < self.states.len() as u64 {
+            return Some(steps as usize);
+        }
+
+        let Cycle { offset, period } = self.cycle?;
+        Some(offset + ((steps - offset as u64) % period as u64) as usize)
+    }
+
+    /// The state after `steps` steps, `None` if it wasn't recorded and no cycle was found.
+    pub fn state(&self, steps: u64) -> Option<&S> {
+        self.index(steps).map(|i| &self.states[i])
+    }
+
+    /// Adds up `f` over the states after each of the first `steps` steps, the starting
+    /// one excluded.
+    pub fn sum(&self, steps: u64, mut f: impl FnMut(&S) -> u64) -> Option<u64> {
+        if steps < self.states.len() as u64 {
+            return Some(self.states[1..=steps as usize].iter().map(f).sum());
+        }
+
+        let Cycle { offset, period } = self.cycle?;
+        // The steps before the cycle, then whole periods, then what's left of one.
+        let first = offset.max(1);
+        let prefix: u64 = self.states[1..first].iter().map(&mut f).sum();
+        let cycle = self.states[offset..].iter().map(f).collect::<Vec<_>>();
+
+        let remaining = steps - first as u64 + 1;
+        let periods = remaining / period as u64;
+        let partial: u64 = (0..(remaining % period as u64) as usize)
+            .map(|j| cycle[(first - offset + j) % period])
+            .sum();
+
+        Some(prefix + periods * cycle.iter().sum::<u64>() + partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle() {
+        // 1, 3, 9, 27 % 20 = 7, 21 % 20 = 1, ...
+        let history = History::record(1u64, |&n| n * 3 % 20, 100);
+
+        assert_eq!(
+            history.cycle(),
+            Some(Cycle {
+                offset: 0,
+                period: 4
+            })
+        );
+        assert_eq!(history.state(1_000_000_001), Some(&3));
+        assert_eq!(history.sum(6, |&n| n), Some(3 + 9 + 7 + 1 + 3 + 9));
+        assert_eq!(history.sum(4_000_000, |&n| n), Some(20 * 1_000_000));
+
+        // 2, 4, 8, 16, 12, 4, ...
+        let history = History::record(2u64, |&n| n * 2 % 20, 100);
+
+        assert_eq!(
+            history.cycle(),
+            Some(Cycle {
+                offset: 1,
+                period: 4
+            })
+        );
+        assert_eq!(history.state(0), Some(&2));
+        assert_eq!(history.state(1_000_000_001), Some(&4));
+        assert_eq!(history.sum(9, |&n| n), Some(40 + 40 + 4));
+    }
+
+    #[test]
+    fn test_limit() {
+        let history = History::record(0u64, |&n| n + 1, 10);
+
+        assert_eq!(history.cycle(), None);
+        assert_eq!(history.state(10), Some(&10));
+        assert_eq!(history.state(11), None);
+        assert_eq!(history.sum(10, |&n| n), Some(55));
+        assert_eq!(history.sum(11, |&n| n), None);
+
+        assert_eq!(state_after(0u64, |&n| n + 1, 1000, 10), 1000);
+        assert_eq!(state_after(1u64, |&n| n * 3 % 20, 1_000_000_001, 10), 3);
+    }
+
+    #[test]
+    fn test_find_step() {
+        assert_eq!(find_step(1u64, |&n| n * 3 % 20, |&n| n == 7), Some(3));
+        assert_eq!(find_step(1u64, |&n| n * 3 % 20, |&n| n == 1), Some(4));
+        assert_eq!(find_step(1u64, |&n| n * 3 % 20, |&n| n == 0), None);
+
+        // A long tail before a long cycle.
+        let step = |&n: &u64| {
+            if n < 1000 {
+                n + 1
+            } else {
+                1000 + (n - 999) % 777
+            }
+        };
+        assert_eq!(find_step(0, step, |&n| n == 1776), Some(1776));
+        assert_eq!(find_step(0, step, |&n| n == 1777), None);
+    }
+}
@@ -1,10 +1,12 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use std::num::ParseIntError;
 
 #[aoc_generator(day1)]
-fn parse_input_day1(input: &str) -> Result<Vec<i32>, ParseIntError> {
-    input.lines().map(|l| l.parse()).collect()
+fn parse_input_day1(input: &str) -> Result<Vec<i32>, ParseError> {
+    let source = Source::new(1, input);
+
+    input.lines().map(|l| source.number(l)).collect()
 }
 
 /// 1696
@@ -1,6 +1,6 @@
 use crate::day02::Directions::{Down, Forward, Up};
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
 
 #[derive(Copy, Clone, Debug)]
 struct Location {
@@ -16,25 +16,30 @@ enum Directions<T> {
     Up(T),
 }
 
-impl From<&str> for Directions<i32> {
-    fn from(s: &str) -> Self {
+impl Directions<i32> {
+    fn parse(source: &Source, s: &str) -> Result<Self, ParseError> {
         let mut parts = s.split_whitespace();
 
-        match parts.next() {
-            Some("forward") => Forward(parts.next().unwrap().parse().unwrap()),
-            Some("down") => Down(parts.next().unwrap().parse().unwrap()),
-            Some("up") => Up(parts.next().unwrap().parse().unwrap()),
-            _ => unreachable!(),
+        let direction = source.next(&mut parts, s, "a direction")?;
+        let n = source.number(source.next(&mut parts, direction, "a distance")?)?;
+
+        match direction {
+            "forward" => Ok(Forward(n)),
+            "down" => Ok(Down(n)),
+            "up" => Ok(Up(n)),
+            _ => Err(source.unexpected(direction)),
         }
     }
 }
 
 #[aoc_generator(day2)]
-fn parse_input_day2(input: &str) -> Result<Vec<Directions<i32>>, ParseIntError> {
-    Ok(input
+fn parse_input_day2(input: &str) -> Result<Vec<Directions<i32>>, ParseError> {
+    let source = Source::new(2, input);
+
+    input
         .lines()
-        .map(Directions::from)
-        .collect::<Vec<Directions<i32>>>())
+        .map(|l| Directions::parse(&source, l))
+        .collect()
 }
 
 #[aoc(day2, part1)]
@@ -86,13 +91,28 @@ mod tests {
     fn test_parser() {
         let input = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2";
         // assert_eq!(parse_input_day2(&input).unwrap(), vec![Forward(5 as i32), Down(5), Forward(8), Up(3), Down(8), Forward(2)]);
-        println!("{:?}", parse_input_day2(&input).unwrap());
+        println!("{:?}", parse_input_day2(input).unwrap());
+    }
+
+    #[test]
+    fn test_parser_error() {
+        let input = "forward 5\nsideways 5";
+        assert_eq!(
+            parse_input_day2(input).unwrap_err().to_string(),
+            "day 2, line 2, column 1: unexpected \"sideways\""
+        );
+
+        let input = "forward 5\ndown";
+        assert_eq!(
+            parse_input_day2(input).unwrap_err().to_string(),
+            "day 2, line 2, column 5: expected a distance"
+        );
     }
 
     #[test]
     fn test_part1() {
         let input = vec![
-            Forward(5 as i32),
+            Forward(5_i32),
             Down(5),
             Forward(8),
             Up(3),
@@ -105,7 +125,7 @@ mod tests {
     #[test]
     fn test_part2() {
         let input = vec![
-            Forward(5 as i32),
+            Forward(5_i32),
             Down(5),
             Forward(8),
             Up(3),
@@ -1,3 +1,4 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 
 struct ParsedInput {
@@ -6,20 +7,25 @@ struct ParsedInput {
 }
 
 #[aoc_generator(day3)]
-fn parse_input_day3(input: &str) -> ParsedInput {
+fn parse_input_day3(input: &str) -> Result<ParsedInput, ParseError> {
+    let source = Source::new(3, input);
+
     let mut entry_length = 0;
     let data = input
         .lines()
         .map(|l| {
             entry_length = l.len();
-            usize::from_str_radix(l, 2).unwrap()
+            usize::from_str_radix(l, 2).map_err(|_| ParseError::InvalidNumber {
+                location: source.locate(l),
+                text: l.to_string(),
+            })
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
-    ParsedInput {
+    Ok(ParsedInput {
         entry_length: entry_length as u32,
         data,
-    }
+    })
 }
 
 struct BitsIterator {
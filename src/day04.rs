@@ -1,5 +1,7 @@
 use crate::day04::BoardTile::{Marked, Unmarked};
-use aoc_runner_derive::aoc;
+use crate::grid::{Grid, Point};
+use crate::parse::{ParseError, Source};
+use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
@@ -8,146 +10,231 @@ enum BoardTile {
     Marked,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum WinRule {
+    /// A complete row or column, or diagonal if enabled.
+    #[default]
+    Line,
+    /// Every number on the card.
+    FullCard,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct Rules {
+    pub win: WinRule,
+    /// Whether the two diagonals of square boards count as lines.
+    pub diagonals: bool,
+}
+
+#[derive(Debug, Clone)]
 struct Board {
-    tiles: Vec<Vec<BoardTile>>,
+    tiles: Grid<BoardTile>,
+}
+
+fn complete<'a>(mut line: impl Iterator<Item = &'a BoardTile>) -> bool {
+    line.all(|t| *t == Marked)
 }
 
 impl Board {
     fn mark(&mut self, value: u32) {
-        for i in 0..5 {
-            for j in 0..5 {
-                if Unmarked(value) == self.tiles[i][j] {
-                    self.tiles[i][j] = Marked
-                }
+        for (_, tile) in self.tiles.iter_mut() {
+            if Unmarked(value) == *tile {
+                *tile = Marked
             }
         }
     }
 
-    fn has_won(&self) -> bool {
-        'check_rows: for i in 0..5 {
-            for j in 0..5 {
-                if let Unmarked(_) = self.tiles[i][j] {
-                    continue 'check_rows;
-                }
+    fn has_won(&self, rules: &Rules) -> bool {
+        match rules.win {
+            WinRule::FullCard => complete(self.tiles.cells().iter()),
+            WinRule::Line => {
+                self.tiles.rows().any(|row| complete(row.iter()))
+                    || self.tiles.columns().any(complete)
+                    || (rules.diagonals && self.has_diagonal())
             }
-
-            return true;
         }
+    }
 
-        'check_columns: for i in 0..5 {
-            for j in 0..5 {
-                if let Unmarked(_) = self.tiles[j][i] {
-                    continue 'check_columns;
-                }
-            }
+    fn has_diagonal(&self) -> bool {
+        let size = self.tiles.width();
 
-            return true;
+        if size != self.tiles.height() {
+            return false;
         }
 
-        false
+        let marked = |p: Point| self.tiles[p] == Marked;
+
+        (0..size).all(|i| marked(Point::new(i, i)))
+            || (0..size).all(|i| marked(Point::new(size - 1 - i, i)))
     }
 
     fn sum_unmarked(&self) -> u32 {
-        let mut sum = 0;
-
-        for i in 0..5 {
-            for j in 0..5 {
-                if let Unmarked(n) = self.tiles[i][j] {
-                    sum += n
-                }
-            }
-        }
-
-        sum
+        self.tiles
+            .cells()
+            .iter()
+            .map(|t| match t {
+                Unmarked(n) => *n,
+                Marked => 0,
+            })
+            .sum()
     }
 }
 
-impl From<Vec<&str>> for Board {
-    fn from(b: Vec<&str>) -> Self {
-        if b.len() != 5 {
-            panic!("board must have 5 columns")
-        }
-
-        let mut tiles = vec![vec![Marked; 5]; 5];
+impl Board {
+    fn parse(source: &Source, b: &str) -> Result<Self, ParseError> {
+        let mut tiles: Vec<Vec<BoardTile>> = Vec::new();
 
-        b.iter().enumerate().for_each(|(i, &row)| {
-            tiles[i] = row
+        for row in b.lines() {
+            let numbers = row
                 .split_whitespace()
-                .map(|s| Unmarked(s.parse().unwrap()))
+                .map(|s| source.number(s).map(Unmarked))
                 .collect_vec();
-        });
 
-        Board { tiles }
+            match tiles.first().map(Vec::len) {
+                Some(width) if numbers.len() < width => {
+                    return Err(source.missing(row, "more numbers in the row"))
+                }
+                Some(width) if numbers.len() > width => {
+                    return Err(source.unexpected(row.split_whitespace().nth(width).unwrap()))
+                }
+                _ => tiles.push(numbers.into_iter().collect::<Result<_, _>>()?),
+            }
+        }
+
+        if tiles.is_empty() || tiles[0].is_empty() {
+            return Err(source.missing(b, "a board"));
+        }
+
+        Ok(Board {
+            tiles: Grid::from_rows(tiles),
+        })
     }
 }
 
-impl Board {}
-
-struct Input {
+#[derive(Clone)]
+pub struct Input {
     sequence: Vec<u32>,
     boards: Vec<Board>,
 }
 
-fn parse_input(input: &str) -> Input {
+#[aoc_generator(day4)]
+pub fn parse_input(input: &str) -> Result<Input, ParseError> {
+    let source = Source::new(4, input);
+
     let mut chunks = input.split_terminator("\n\n");
 
     let mut input = Input {
-        sequence: chunks
-            .next()
-            .unwrap()
+        sequence: source
+            .next(&mut chunks, input, "the drawn numbers")?
             .split(',')
-            .map(|n| n.parse().unwrap())
-            .collect(),
+            .map(|n| source.number(n))
+            .collect::<Result<_, _>>()?,
         boards: chunks
-            .into_iter()
-            .map(|b| Board::from(b.lines().collect_vec()))
-            .collect_vec(),
+            .map(|b| Board::parse(&source, b))
+            .collect::<Result<_, _>>()?,
     };
 
     input.sequence.reverse();
 
-    input
+    Ok(input)
 }
 
-#[aoc(day4, part1)]
-fn day4_part1(input: &str) -> Option<u32> {
-    let mut input = parse_input(input);
+/// A board completing on a draw, `board` being its position in the input.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Win {
+    pub board: usize,
+    pub score: u32,
+}
 
-    loop {
-        let number = input.sequence.pop().unwrap();
-        for board in input.boards.iter_mut() {
-            board.mark(number);
+/// One step of the game: the number drawn and the boards that won because of it, in
+/// input order.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Draw {
+    pub number: u32,
+    pub winners: Vec<Win>,
+}
 
-            if board.has_won() {
-                return Some(board.sum_unmarked() * number);
-            }
+/// Replays the draws one at a time, yielding a [`Draw`] for each number. Boards stop
+/// playing once they won.
+pub struct BingoGame {
+    rules: Rules,
+    sequence: Vec<u32>,
+    boards: Vec<Board>,
+    already_won_boards: Vec<bool>,
+}
+
+impl BingoGame {
+    pub fn new(input: &Input, rules: Rules) -> Self {
+        BingoGame {
+            rules,
+            sequence: input.sequence.clone(),
+            boards: input.boards.clone(),
+            already_won_boards: vec![false; input.boards.len()],
         }
     }
+
+    /// Every win of the game, in the order they happened.
+    pub fn wins(self) -> impl Iterator<Item = Win> {
+        self.flat_map(|draw| draw.winners)
+    }
+
+    /// How many numbers have to be drawn before `count` boards won.
+    pub fn draws_until_won(self, count: usize) -> Option<usize> {
+        if count == 0 {
+            return Some(0);
+        }
+
+        self.scan(0, |won, draw| {
+            *won += draw.winners.len();
+            Some(*won)
+        })
+        .position(|won| won >= count)
+        .map(|i| i + 1)
+    }
 }
 
-#[aoc(day4, part2)]
-fn day4_part2(input: &str) -> Option<u32> {
-    let mut input = parse_input(input);
+impl Iterator for BingoGame {
+    type Item = Draw;
 
-    let mut last_winning_score = 0;
-    let mut already_won_boards = Vec::with_capacity(input.boards.len());
+    fn next(&mut self) -> Option<Self::Item> {
+        let number = self.sequence.pop()?;
+        let mut winners = Vec::new();
 
-    while !input.sequence.is_empty() {
-        let number = input.sequence.pop().unwrap();
+        for (i, board) in self.boards.iter_mut().enumerate() {
+            if self.already_won_boards[i] {
+                continue;
+            }
 
-        for (i, board) in input.boards.iter_mut().enumerate() {
             board.mark(number);
 
-            if already_won_boards.iter().all(|test| *test != i) && board.has_won() {
-                last_winning_score = board.sum_unmarked() * number;
+            if board.has_won(&self.rules) {
+                winners.push(Win {
+                    board: i,
+                    score: board.sum_unmarked() * number,
+                });
 
-                already_won_boards.push(i);
+                self.already_won_boards[i] = true;
             }
         }
+
+        Some(Draw { number, winners })
     }
+}
+
+#[aoc(day4, part1)]
+fn day4_part1(input: &Input) -> Option<u32> {
+    BingoGame::new(input, Rules::default())
+        .wins()
+        .next()
+        .map(|w| w.score)
+}
 
-    Some(last_winning_score)
+#[aoc(day4, part2)]
+fn day4_part2(input: &Input) -> Option<u32> {
+    BingoGame::new(input, Rules::default())
+        .wins()
+        .last()
+        .map(|w| w.score)
 }
 
 #[cfg(test)]
@@ -176,7 +263,7 @@ mod tests {
 22 11 13  6  5
  2  0 12  3  7";
 
-        let input = parse_input(&input);
+        let input = parse_input(input).unwrap();
 
         let mut sequence = vec![
             7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18, 20, 8, 19,
@@ -187,7 +274,7 @@ mod tests {
         assert_eq!(input.sequence, sequence);
         assert_eq!(
             input.boards.get(2).unwrap().tiles,
-            vec![
+            Grid::from_rows(vec![
                 vec![
                     Unmarked(14),
                     Unmarked(21),
@@ -223,7 +310,7 @@ mod tests {
                     Unmarked(3),
                     Unmarked(7),
                 ],
-            ]
+            ])
         );
     }
 
@@ -249,7 +336,7 @@ mod tests {
 22 11 13  6  5
  2  0 12  3  7";
 
-        assert_eq!(day4_part1(&input), Some(4512));
+        assert_eq!(day4_part1(&parse_input(input).unwrap()), Some(4512));
     }
 
     #[test]
@@ -274,6 +361,106 @@ mod tests {
 22 11 13  6  5
  2  0 12  3  7";
 
-        assert_eq!(day4_part2(&input), Some(1924));
+        assert_eq!(day4_part2(&parse_input(input).unwrap()), Some(1924));
+    }
+
+    #[test]
+    fn test_rules() {
+        let input = "1,5,9,3,7,2
+
+1 2 3
+4 5 6
+7 8 9
+
+1 2
+3 4
+5 6";
+        let input = parse_input(input).unwrap();
+
+        assert_eq!(input.boards[0].tiles.width(), 3);
+        assert_eq!(input.boards[1].tiles.height(), 3);
+
+        let play = |rules| {
+            BingoGame::new(&input, rules)
+                .wins()
+                .map(|w| w.score)
+                .collect_vec()
+        };
+
+        // 1, 5, 3 complete the first column of the 2 wide board before 1, 2, 3 complete
+        // the first row of the square one, which wins first once 1, 5, 9 counts.
+        assert_eq!(
+            play(Rules::default()),
+            vec![(2 + 4 + 6) * 3, (4 + 6 + 8) * 2]
+        );
+        assert_eq!(
+            play(Rules {
+                diagonals: true,
+                ..Default::default()
+            }),
+            vec![(2 + 3 + 4 + 6 + 7 + 8) * 9, (2 + 4 + 6) * 3]
+        );
+
+        let full_card = Rules {
+            win: WinRule::FullCard,
+            ..Default::default()
+        };
+        assert_eq!(play(full_card), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_timeline() {
+        let input = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7";
+        let input = parse_input(input).unwrap();
+        let game = || BingoGame::new(&input, Rules::default());
+
+        assert_eq!(
+            game().nth(11),
+            Some(Draw {
+                number: 24,
+                winners: vec![Win {
+                    board: 2,
+                    score: 4512
+                }],
+            })
+        );
+        assert_eq!(
+            game().wins().collect_vec(),
+            vec![
+                Win {
+                    board: 2,
+                    score: 4512
+                },
+                Win {
+                    board: 0,
+                    score: 2192
+                },
+                Win {
+                    board: 1,
+                    score: 1924
+                },
+            ]
+        );
+        assert_eq!(game().count(), 27);
+        assert_eq!(game().draws_until_won(2), Some(14));
+        assert_eq!(game().draws_until_won(4), None);
     }
 }
@@ -1,13 +1,12 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
 use regex::Regex;
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Point {
@@ -17,36 +16,79 @@ impl Point {
     }
 }
 
-#[derive(Eq, PartialEq)]
-enum Strategy {
+#[derive(Eq, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// Horizontal and vertical lines only.
     Vh,
+    /// Horizontal, vertical and 45° diagonal lines.
     Vhd,
+    /// Lines of any slope.
+    Any,
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
-struct Line {
-    start: Point,
-    end: Point,
+pub struct Line {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// The infinite line a segment lies on: its primitive direction `(a, b)`, pointing right
+/// or down, and `c = b * x - a * y`, which is the same for every point of the line.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+struct Carrier {
+    a: i64,
+    b: i64,
+    c: i64,
+}
+
+impl Carrier {
+    /// Orders the lattice points of the line, consecutive ones being `a² + b²` apart.
+    fn position(&self, p: Point) -> i64 {
+        self.a * p.x as i64 + self.b * p.y as i64
+    }
+
+    fn step(&self) -> i64 {
+        self.a * self.a + self.b * self.b
+    }
 }
 
 impl Line {
-    fn points_in_between(&self, s: Strategy) -> Vec<Point> {
-        let diff_x = match self.start.x.cmp(&self.end.x) {
-            Ordering::Less => 1,
-            Ordering::Equal => 0,
-            Ordering::Greater => -1,
-        };
+    /// The step between two consecutive lattice points of the line, zero for a single point.
+    pub fn direction(&self) -> (i32, i32) {
+        let diff_x = self.end.x - self.start.x;
+        let diff_y = self.end.y - self.start.y;
 
-        let diff_y = match self.start.y.cmp(&self.end.y) {
-            Ordering::Less => 1,
-            Ordering::Equal => 0,
-            Ordering::Greater => -1,
-        };
+        match gcd(diff_x, diff_y) {
+            0 => (0, 0),
+            g => (diff_x / g, diff_y / g),
+        }
+    }
+
+    pub fn is_allowed(&self, s: Strategy) -> bool {
+        let (diff_x, diff_y) = self.direction();
+
+        match s {
+            Strategy::Vh => diff_x == 0 || diff_y == 0,
+            Strategy::Vhd => diff_x == 0 || diff_y == 0 || diff_x.abs() == diff_y.abs(),
+            Strategy::Any => true,
+        }
+    }
 
-        if s == Strategy::Vh && diff_x != 0 && diff_y != 0 {
+    pub fn points_in_between(&self, s: Strategy) -> Vec<Point> {
+        if !self.is_allowed(s) {
             return Vec::new();
         }
 
+        let (diff_x, diff_y) = self.direction();
+
         let mut moving_point = self.start;
 
         let mut result = vec![moving_point];
@@ -58,65 +100,186 @@ impl Line {
 
         result
     }
+
+    fn carrier(&self) -> Carrier {
+        let (a, b) = match self.direction() {
+            // A single point is given a horizontal line.
+            (0, 0) => (1, 0),
+            (a, b) if a < 0 || (a == 0 && b < 0) => (-a, -b),
+            (a, b) => (a, b),
+        };
+        let (a, b) = (a as i64, b as i64);
+
+        Carrier {
+            a,
+            b,
+            c: b * self.start.x as i64 - a * self.start.y as i64,
+        }
+    }
+
+    /// The lattice point where two non parallel segments cross, if any.
+    fn crossing(&self, other: &Line) -> Option<Point> {
+        // A single point may take any direction that isn't parallel to the other line.
+        let direction = |l: &Line, o: &Line| match (l.direction(), o.direction()) {
+            ((0, 0), (_, 0)) => (0, 1),
+            ((0, 0), _) => (1, 0),
+            (d, _) => d,
+        };
+        let (dx1, dy1) = direction(self, other);
+        let (dx2, dy2) = direction(other, self);
+        let (dx1, dy1, dx2, dy2) = (dx1 as i64, dy1 as i64, dx2 as i64, dy2 as i64);
+
+        let denominator = dx1 * dy2 - dy1 * dx2;
+        if denominator == 0 {
+            return None;
+        }
+
+        let qx = (other.start.x - self.start.x) as i64;
+        let qy = (other.start.y - self.start.y) as i64;
+
+        let t = qx * dy2 - qy * dx2;
+        let u = qx * dy1 - qy * dx1;
+
+        if t % denominator != 0 || u % denominator != 0 {
+            return None;
+        }
+
+        let (t, u) = (t / denominator, u / denominator);
+
+        if (0..=self.length()).contains(&t) && (0..=other.length()).contains(&u) {
+            Some(Point {
+                x: (self.start.x as i64 + t * dx1) as i32,
+                y: (self.start.y as i64 + t * dy1) as i32,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The number of steps of [`Line::direction`] from start to end.
+    fn length(&self) -> i64 {
+        gcd(self.end.x - self.start.x, self.end.y - self.start.y) as i64
+    }
+}
+
+/// Counts the lattice points covered by at least two of the lines without visiting
+/// each covered point: segments on the same line are swept along their sorted ends,
+/// while crossing segments meet in at most one point.
+pub fn count_overlaps(lines: &[Line], s: Strategy) -> usize {
+    let lines = lines.iter().filter(|l| l.is_allowed(s)).collect::<Vec<_>>();
+
+    let mut carriers: HashMap<Carrier, Vec<(i64, i64)>> = HashMap::new();
+    for line in lines.iter() {
+        let carrier = line.carrier();
+        let (from, to) = (carrier.position(line.start), carrier.position(line.end));
+
+        carriers
+            .entry(carrier)
+            .or_default()
+            .push((from.min(to), from.max(to)));
+    }
+
+    // For every line, the ranges of positions covered at least twice.
+    let mut overlaps: HashMap<Carrier, Vec<(i64, i64)>> = HashMap::new();
+    let mut count = 0;
+
+    for (carrier, segments) in carriers.iter() {
+        let step = carrier.step();
+
+        let mut events = segments
+            .iter()
+            .flat_map(|&(from, to)| [(from, 1), (to + step, -1)])
+            .collect::<Vec<_>>();
+        events.sort_unstable();
+
+        let mut coverage = 0;
+        for (i, &(position, change)) in events.iter().enumerate() {
+            coverage += change;
+
+            if let Some(&(next, _)) = events.get(i + 1) {
+                if coverage >= 2 && next > position {
+                    count += ((next - position) / step) as usize;
+                    overlaps
+                        .entry(*carrier)
+                        .or_default()
+                        .push((position, next - step));
+                }
+            }
+        }
+    }
+
+    let covered_twice = |carrier: &Carrier, p: Point| {
+        let position = carrier.position(p);
+
+        overlaps.get(carrier).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|&(from, to)| (from..=to).contains(&position))
+        })
+    };
+
+    // Every line through a crossing crosses one of the first two found there, so the
+    // carriers collected for a point are all the lines through it.
+    let mut crossings: HashMap<Point, HashSet<Carrier>> = HashMap::new();
+    for (i, first) in lines.iter().enumerate() {
+        for second in lines.iter().skip(i + 1) {
+            if let Some(p) = first.crossing(second) {
+                let carriers = crossings.entry(p).or_default();
+                carriers.insert(first.carrier());
+                carriers.insert(second.carrier());
+            }
+        }
+    }
+
+    // A crossing is new unless some line already covers it twice, and it was counted
+    // once too many for every extra line doing so.
+    crossings.iter().fold(count, |count, (&p, carriers)| {
+        match carriers.iter().filter(|c| covered_twice(c, p)).count() {
+            0 => count + 1,
+            n => count + 1 - n,
+        }
+    })
 }
 
 #[aoc_generator(day5)]
-fn parse_input_day5(input: &str) -> Vec<Line> {
-    let regex = Regex::new(r"(\d+),(\d+) -> (\d+),(\d+)").unwrap();
+fn parse_input_day5(input: &str) -> Result<Vec<Line>, ParseError> {
+    let source = Source::new(5, input);
+    let regex = Regex::new(r"^(\d+),(\d+) -> (\d+),(\d+)$").unwrap();
 
     input
         .lines()
         .map(|l| {
-            let iter = regex.captures(l).unwrap();
-            Line {
+            let iter = regex.captures(l).ok_or_else(|| source.unexpected(l))?;
+            let number = |i| source.number(iter.get(i).unwrap().as_str());
+
+            Ok(Line {
                 start: Point {
-                    x: iter.get(1).unwrap().as_str().parse().unwrap(),
-                    y: iter.get(2).unwrap().as_str().parse().unwrap(),
+                    x: number(1)?,
+                    y: number(2)?,
                 },
                 end: Point {
-                    x: iter.get(3).unwrap().as_str().parse().unwrap(),
-                    y: iter.get(4).unwrap().as_str().parse().unwrap(),
+                    x: number(3)?,
+                    y: number(4)?,
                 },
-            }
+            })
         })
-        .collect_vec()
+        .collect()
 }
 
 #[aoc(day5, part1)]
 fn day5_part1(input: &[Line]) -> Option<usize> {
-    input
-        .iter()
-        .fold(HashMap::<Point, u32>::new(), |mut diagram, line| {
-            line.points_in_between(Strategy::Vh)
-                .iter()
-                .for_each(|p| *diagram.entry(*p).or_default() += 1);
-            diagram
-        })
-        .iter()
-        .filter(|(_, v)| v > &&1)
-        .count()
-        .into()
+    Some(count_overlaps(input, Strategy::Vh))
 }
 
 #[aoc(day5, part2)]
 fn day5_part2(input: &[Line]) -> Option<usize> {
-    input
-        .iter()
-        .fold(HashMap::<Point, u32>::new(), |mut diagram, line| {
-            line.points_in_between(Strategy::Vhd)
-                .iter()
-                .for_each(|p| *diagram.entry(*p).or_default() += 1);
-            diagram
-        })
-        .iter()
-        .filter(|(_, v)| v > &&1)
-        .count()
-        .into()
+    Some(count_overlaps(input, Strategy::Vhd))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Rng;
 
     #[test]
     fn test_parse_input() {
@@ -132,7 +295,7 @@ mod tests {
 5,5 -> 8,2";
 
         assert_eq!(
-            parse_input_day5(&input),
+            parse_input_day5(input).unwrap(),
             vec![
                 Line {
                     start: Point { x: 0, y: 9 },
@@ -211,7 +374,7 @@ mod tests {
 0,0 -> 8,8
 5,5 -> 8,2";
 
-        assert_eq!(day5_part1(&parse_input_day5(&input)), Some(5));
+        assert_eq!(day5_part1(&parse_input_day5(input).unwrap()), Some(5));
     }
 
     #[test]
@@ -227,6 +390,81 @@ mod tests {
 0,0 -> 8,8
 5,5 -> 8,2";
 
-        assert_eq!(day5_part2(&parse_input_day5(&input)), Some(12));
+        assert_eq!(day5_part2(&parse_input_day5(input).unwrap()), Some(12));
+    }
+
+    fn count_by_drawing(lines: &[Line], s: Strategy) -> usize {
+        lines
+            .iter()
+            .fold(HashMap::<Point, u32>::new(), |mut diagram, line| {
+                line.points_in_between(s)
+                    .iter()
+                    .for_each(|p| *diagram.entry(*p).or_default() += 1);
+                diagram
+            })
+            .values()
+            .filter(|&&v| v > 1)
+            .count()
+    }
+
+    #[test]
+    fn test_any_slope() {
+        let l = Line {
+            start: Point { x: 1, y: 7 },
+            end: Point { x: 7, y: -2 },
+        };
+
+        assert!(l.points_in_between(Strategy::Vhd).is_empty());
+        assert_eq!(
+            l.points_in_between(Strategy::Any),
+            vec![
+                Point { x: 1, y: 7 },
+                Point { x: 3, y: 4 },
+                Point { x: 5, y: 1 },
+                Point { x: 7, y: -2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_overlaps_matches_drawing() {
+        let mut rng = Rng::new(5);
+        let mut coordinate = || rng.below(8) as i32 - 2;
+
+        for _ in 0..200 {
+            let lines = (0..16)
+                .map(|_| Line {
+                    start: Point {
+                        x: coordinate(),
+                        y: coordinate(),
+                    },
+                    end: Point {
+                        x: coordinate(),
+                        y: coordinate(),
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            for s in [Strategy::Vh, Strategy::Vhd, Strategy::Any] {
+                assert_eq!(count_overlaps(&lines, s), count_by_drawing(&lines, s));
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_overlaps_large() {
+        let input = "0,0 -> 3000000,3000000
+1000000,0 -> 1000000,5000000
+0,4000000 -> 4000000,0
+2000000,2000000 -> 5000000,5000000
+1000000,2000000 -> 1000000,8000000";
+
+        // The vertical lines share three million and one points and the diagonals one
+        // million and one. The anti diagonal only crosses them where they already
+        // overlap, leaving (1000000, 1000000) as the single extra crossing.
+        assert_eq!(
+            count_overlaps(&parse_input_day5(input).unwrap(), Strategy::Vhd),
+            3000001 + 1000001 + 1
+        );
     }
 }
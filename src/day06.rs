@@ -1,40 +1,243 @@
+use crate::bigint::BigUint;
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
 
 #[aoc_generator(day6)]
-fn parse_input_day6(input: &str) -> Result<Vec<i32>, ParseIntError> {
-    input.split_terminator(',').map(|l| l.parse()).collect()
+fn parse_input_day6(input: &str) -> Result<Vec<i32>, ParseError> {
+    let source = Source::new(6, input);
+
+    input
+        .split_terminator(',')
+        .map(|l| match source.number(l)? {
+            n @ 0..=8 => Ok(n),
+            _ => Err(source.unexpected(l)),
+        })
+        .collect()
 }
 
 #[aoc(day6, part1)]
 fn day6_part1(input: &[i32]) -> Option<usize> {
-    Some(solution(input, 80))
+    let model = LanternfishModel::default();
+
+    model.simulate(input, 80).to_u64().map(|n| n as usize)
 }
 
 #[aoc(day6, part2)]
 fn day6_part2(input: &[i32]) -> Option<usize> {
-    Some(solution(input, 256))
+    let model = LanternfishModel::default();
+
+    model.simulate(input, 256).to_u64().map(|n| n as usize)
+}
+
+#[aoc(day6, part1, matrix)]
+fn day6_part1_matrix(input: &[i32]) -> Option<BigUint> {
+    Some(LanternfishModel::default().population(input, 80, None))
+}
+
+#[aoc(day6, part2, matrix)]
+fn day6_part2_matrix(input: &[i32]) -> Option<BigUint> {
+    Some(LanternfishModel::default().population(input, 256, None))
+}
+
+/// The most (age, timer) states [`LanternfishModel::population`] takes on, which allows
+/// lifespans up to 14 days with the default timers.
+pub const MAX_MATRIX_STATES: usize = 128;
+
+/// How lanternfish reproduce. Every day a fish's timer goes down by one, except at 0
+/// where it goes back to `reset_timer` and `offspring` new fish appear with a timer of
+/// `newborn_timer`.
+#[derive(Debug, Clone)]
+pub struct LanternfishModel {
+    pub reset_timer: usize,
+    pub newborn_timer: usize,
+    pub offspring: u64,
+    /// The number of days a fish lives, counting from the start for the initial fish.
+    /// A fish may still spawn on its last day.
+    pub lifespan: Option<usize>,
+}
+
+impl Default for LanternfishModel {
+    fn default() -> Self {
+        LanternfishModel {
+            reset_timer: 6,
+            newborn_timer: 8,
+            offspring: 1,
+            lifespan: None,
+        }
+    }
+}
+
+impl LanternfishModel {
+    /// The fish are grouped in states of (age, timer), stored at `age * timers + timer`.
+    /// Ages are only told apart when fish have a lifespan.
+    fn states(&self, input: &[i32]) -> (usize, usize) {
+        let timers = input
+            .iter()
+            .map(|&t| t as usize)
+            .chain([self.reset_timer, self.newborn_timer])
+            .max()
+            .unwrap_or_default()
+            + 1;
+
+        (timers, timers * self.lifespan.unwrap_or(1))
+    }
+
+    /// The states a single fish in `state` turns into the next day, with their counts.
+    fn successors(&self, timers: usize, state: usize) -> Vec<(usize, u64)> {
+        let (age, timer) = (state / timers, state % timers);
+
+        let next_age = match self.lifespan {
+            Some(lifespan) if age + 1 >= lifespan => None,
+            Some(_) => Some(age + 1),
+            None => Some(0),
+        };
+
+        let mut successors = Vec::new();
+        if timer == 0 {
+            if let Some(next_age) = next_age {
+                successors.push((next_age * timers + self.reset_timer, 1));
+            }
+            successors.push((self.newborn_timer, self.offspring));
+        } else if let Some(next_age) = next_age {
+            successors.push((next_age * timers + timer - 1, 1));
+        }
+
+        successors
+    }
+
+    fn initial(&self, input: &[i32], states: usize) -> Vec<BigUint> {
+        let mut counts = vec![0; states];
+        for &timer in input {
+            counts[timer as usize] += 1;
+        }
+
+        counts.into_iter().map(BigUint::from).collect()
+    }
+
+    /// The number of fish after `days`, stepping through every day.
+    pub fn simulate(&self, input: &[i32], days: usize) -> BigUint {
+        let (timers, states) = self.states(input);
+        let mut counts = self.initial(input, states);
+
+        for _ in 0..days {
+            let mut next = vec![BigUint::zero(); states];
+
+            for (state, count) in counts.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+                for (successor, n) in self.successors(timers, state) {
+                    next[successor] = &next[successor] + &(count * &BigUint::from(n));
+                }
+            }
+
+            counts = next;
+        }
+
+        counts.iter().fold(BigUint::zero(), |sum, c| &sum + c)
+    }
+
+    /// The number of fish after `days`, computed by raising the daily transition to the
+    /// power of `days`. With a `modulus`, usually a prime, the count is given modulo it,
+    /// which keeps the numbers small enough for huge values of `days`.
+    ///
+    /// The transition has a row per state, so it costs `O(states³ log days)`, and a
+    /// lifespan multiplies the states. Models with more than [`MAX_MATRIX_STATES`] are
+    /// rejected, [`LanternfishModel::simulate`] handles them in `O(states × days)`.
+    pub fn population(&self, input: &[i32], days: u64, modulus: Option<u64>) -> BigUint {
+        let (timers, states) = self.states(input);
+        assert!(
+            states <= MAX_MATRIX_STATES,
+            "{} states are too many for the matrix, simulate the days instead",
+            states
+        );
+
+        // `transition.cells[i][j]` is how many fish in state `i` a fish in state `j`
+        // turns into the next day.
+        let mut transition = Matrix::zero(states, modulus);
+        for j in 0..states {
+            for (i, n) in self.successors(timers, j) {
+                transition.cells[i][j] = &transition.cells[i][j] + &BigUint::from(n);
+            }
+        }
+
+        let transition = transition.pow(days);
+        let initial = self.initial(input, states);
+
+        let mut total = BigUint::zero();
+        for row in transition.cells.iter() {
+            for (cell, count) in row.iter().zip(initial.iter()) {
+                total = &total + &(cell * count);
+            }
+            total = transition.reduce(total);
+        }
+
+        total
+    }
 }
 
-fn solution(input: &[i32], days: usize) -> usize {
-    let mut fish = [0; 9];
+/// A square matrix of counts, optionally kept reduced modulo `modulus`.
+#[derive(Clone)]
+struct Matrix {
+    cells: Vec<Vec<BigUint>>,
+    modulus: Option<u64>,
+}
 
-    for i in input {
-        fish[*i as usize] += 1;
+impl Matrix {
+    fn zero(size: usize, modulus: Option<u64>) -> Self {
+        Matrix {
+            cells: vec![vec![BigUint::zero(); size]; size],
+            modulus,
+        }
     }
 
-    for _ in 0..days {
-        let temp = fish[0];
+    fn identity(size: usize, modulus: Option<u64>) -> Self {
+        let mut identity = Matrix::zero(size, modulus);
+        for i in 0..size {
+            identity.cells[i][i] = BigUint::from(1);
+        }
+
+        identity
+    }
 
-        for i in 0..8 {
-            fish[i] = fish[i + 1];
+    fn reduce(&self, n: BigUint) -> BigUint {
+        match self.modulus {
+            Some(m) => BigUint::from(n.rem(m)),
+            None => n,
         }
-        fish[8] = temp;
+    }
+
+    fn mul(&self, other: &Matrix) -> Matrix {
+        let size = self.cells.len();
 
-        fish[6] += fish[8];
+        Matrix {
+            cells: (0..size)
+                .map(|i| {
+                    (0..size)
+                        .map(|j| {
+                            let sum = (0..size).fold(BigUint::zero(), |sum, k| {
+                                &sum + &(&self.cells[i][k] * &other.cells[k][j])
+                            });
+                            self.reduce(sum)
+                        })
+                        .collect()
+                })
+                .collect(),
+            modulus: self.modulus,
+        }
     }
 
-    fish.iter().sum()
+    fn pow(&self, mut exponent: u64) -> Matrix {
+        let mut result = Matrix::identity(self.cells.len(), self.modulus);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exponent >>= 1;
+        }
+
+        result
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +255,82 @@ mod tests {
         let input = vec![3, 4, 3, 1, 2];
         assert_eq!(day6_part2(&input), Some(26984457539));
     }
+
+    #[test]
+    fn test_population() {
+        let input = vec![3, 4, 3, 1, 2];
+        let population =
+            |input, days, modulus| LanternfishModel::default().population(input, days, modulus);
+
+        assert_eq!(population(&input, 18, None), BigUint::from(26));
+        assert_eq!(population(&input, 80, None), BigUint::from(5934));
+        assert_eq!(population(&input, 256, None), BigUint::from(26984457539));
+        assert_eq!(
+            population(&input, 256, Some(1_000_000_007)),
+            BigUint::from(26984457539 % 1_000_000_007)
+        );
+        assert_eq!(population(&input, 10_000, None).to_string().len(), 380);
+        assert_eq!(
+            population(&input, 1_000_000_000_000, Some(998_244_353)),
+            BigUint::from(267489586)
+        );
+    }
+
+    #[test]
+    fn test_models() {
+        let input = vec![3, 4, 3, 1, 2];
+
+        let twins = LanternfishModel {
+            offspring: 2,
+            ..Default::default()
+        };
+        let mortal = LanternfishModel {
+            lifespan: Some(5),
+            ..Default::default()
+        };
+        let quick = LanternfishModel {
+            reset_timer: 2,
+            newborn_timer: 3,
+            ..Default::default()
+        };
+
+        for model in [LanternfishModel::default(), twins, mortal, quick] {
+            for days in [0, 1, 18, 40] {
+                assert_eq!(
+                    model.simulate(&input, days),
+                    model.population(&input, days as u64, None)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too many for the matrix")]
+    fn test_population_limit() {
+        let model = LanternfishModel {
+            lifespan: Some(100),
+            ..Default::default()
+        };
+
+        model.population(&[3, 4, 3, 1, 2], 80, None);
+    }
+
+    #[test]
+    fn test_lifespan() {
+        // Every fish spawns every 3 days and lives for 5 days.
+        let model = LanternfishModel {
+            reset_timer: 2,
+            newborn_timer: 2,
+            offspring: 1,
+            lifespan: Some(5),
+        };
+
+        let counts = (0..=7)
+            .map(|days| model.simulate(&[0], days).to_u64().unwrap())
+            .collect::<Vec<_>>();
+
+        // The first fish spawns on days 1 and 4 and dies at the end of day 5, its first
+        // child spawns on day 4 and dies on day 6, the other two spawn on day 7.
+        assert_eq!(counts, vec![1, 2, 2, 2, 4, 3, 2, 4]);
+    }
 }
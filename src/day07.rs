@@ -1,53 +1,266 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
+use std::collections::HashSet;
 
-#[aoc_generator(day7)]
-fn parse_input_day7(input: &str) -> Result<Vec<i32>, ParseIntError> {
-    input.split(',').map(|l| l.parse()).collect()
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Crab {
+    pub position: i32,
+    /// How many times the crab's fuel counts, 1 unless given as `position:weight`.
+    pub weight: u32,
 }
 
-#[aoc(day7, part1)]
-fn day7_part1(input: &[i32]) -> i32 {
-    let mut input = input.to_vec();
+impl Crab {
+    pub fn new(position: i32) -> Self {
+        Crab {
+            position,
+            weight: 1,
+        }
+    }
+}
 
-    input.sort_unstable();
+#[aoc_generator(day7)]
+fn parse_input_day7(input: &str) -> Result<Vec<Crab>, ParseError> {
+    let source = Source::new(7, input);
 
-    let point = input[input.len() / 2];
     input
-        .iter()
-        .fold(0, |sum, crap| sum + i32::abs(crap - point))
+        .split(',')
+        .map(|l| match l.split_once(':') {
+            Some((position, weight)) => Ok(Crab {
+                position: source.number(position)?,
+                weight: source.number(weight)?,
+            }),
+            None => Ok(Crab::new(source.number(l)?)),
+        })
+        .collect()
 }
 
-#[aoc(day7, part2)]
-fn day7_part2(input: &[i32]) -> i32 {
-    let mut best_fuel = i32::MAX;
+/// The best position found by a [`CrabAligner`] and the fuel needed to reach it.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Alignment {
+    pub position: i32,
+    pub fuel: i64,
+}
+
+/// Finds where crabs should line up to spend the least fuel, `cost` being the fuel one
+/// crab spends to move a distance. The cost has to be convex and non decreasing.
+pub struct CrabAligner<F> {
+    cost: F,
+    excluded: HashSet<i32>,
+}
+
+impl CrabAligner<fn(i64) -> i64> {
+    /// One unit of fuel per step, part 1.
+    pub fn linear() -> Self {
+        CrabAligner::new(|d| d)
+    }
+
+    /// One more unit of fuel for every step than for the previous one, part 2.
+    pub fn triangular() -> Self {
+        CrabAligner::new(|d| d * (d + 1) / 2)
+    }
+
+    pub fn quadratic() -> Self {
+        CrabAligner::new(|d| d * d)
+    }
+}
+
+impl<F: Fn(i64) -> i64> CrabAligner<F> {
+    pub fn new(cost: F) -> Self {
+        CrabAligner {
+            cost,
+            excluded: HashSet::new(),
+        }
+    }
+
+    /// Forbids lining up on any of `positions`.
+    pub fn excluding(mut self, positions: impl IntoIterator<Item = i32>) -> Self {
+        self.excluded.extend(positions);
+        self
+    }
+
+    /// The fuel all crabs spend to reach `position`.
+    pub fn fuel(&self, crabs: &[Crab], position: i32) -> i64 {
+        crabs
+            .iter()
+            .map(|crab| {
+                crab.weight as i64 * (self.cost)((crab.position as i64 - position as i64).abs())
+            })
+            .sum()
+    }
+
+    /// The allowed position needing the least fuel, the leftmost one on a tie. As the
+    /// total fuel is convex too, the best position is the first one from which moving
+    /// right doesn't save any fuel, or the closest allowed one on either side of it.
+    pub fn align(&self, crabs: &[Crab]) -> Option<Alignment> {
+        let positions = crabs.iter().map(|c| c.position);
+        let (mut low, mut high) = (positions.clone().min()?, positions.max()?);
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+
+            if self.fuel(crabs, middle + 1) >= self.fuel(crabs, middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+
+        let allowed = |mut position: i32, step: i32| {
+            while self.excluded.contains(&position) {
+                position += step;
+            }
 
-    let input = input.to_vec();
-    let min_test = input.iter().cloned().fold(i32::MAX, i32::min);
-    let max_test = input.iter().cloned().fold(i32::MIN, i32::max);
+            Alignment {
+                position,
+                fuel: self.fuel(crabs, position),
+            }
+        };
 
-    for i in min_test..max_test {
-        best_fuel = best_fuel.min(input.iter().fold(0, |sum, crap| {
-            sum + (i32::abs(crap - i) * (i32::abs(crap - i) + 1)) / 2
-        }))
+        let (left, right) = (allowed(low, -1), allowed(low, 1));
+
+        Some(if right.fuel < left.fuel { right } else { left })
     }
+}
+
+/// The position splitting the crabs' weight in half, where [`CrabAligner::linear`]
+/// lines them up when nothing is excluded.
+pub fn weighted_median(crabs: &[Crab]) -> Option<i32> {
+    let mut crabs = crabs.to_vec();
+    crabs.sort_unstable_by_key(|c| c.position);
+
+    let total = crabs.iter().map(|c| c.weight as u64).sum::<u64>();
+
+    let mut seen = 0;
+    crabs
+        .iter()
+        .find(|c| {
+            seen += c.weight as u64;
+            2 * seen >= total
+        })
+        .map(|c| c.position)
+}
 
-    best_fuel
+#[aoc(day7, part1)]
+fn day7_part1(input: &[Crab]) -> Option<i64> {
+    CrabAligner::linear().align(input).map(|a| a.fuel)
+}
+
+#[aoc(day7, part2)]
+fn day7_part2(input: &[Crab]) -> Option<i64> {
+    CrabAligner::triangular().align(input).map(|a| a.fuel)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Rng;
+
+    fn crabs(positions: &[i32]) -> Vec<Crab> {
+        positions.iter().map(|&p| Crab::new(p)).collect()
+    }
 
     #[test]
     fn test_part1() {
-        let input = vec![16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
-        assert_eq!(day7_part1(&input), 37);
+        let input = parse_input_day7("16,1,2,0,4,2,7,1,2,14").unwrap();
+        assert_eq!(day7_part1(&input), Some(37));
     }
 
     #[test]
     fn test_part2() {
-        let input = vec![16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
-        assert_eq!(day7_part2(&input), 168);
+        let input = parse_input_day7("16,1,2,0,4,2,7,1,2,14").unwrap();
+        assert_eq!(day7_part2(&input), Some(168));
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let brute_force = |aligner: &CrabAligner<fn(i64) -> i64>, crabs: &[Crab]| {
+            let positions = crabs.iter().map(|c| c.position);
+            let margin = aligner.excluded.len() as i32 + 1;
+            let (min, max) = (positions.clone().min()?, positions.max()?);
+
+            (min - margin..=max + margin)
+                .filter(|p| !aligner.excluded.contains(p))
+                .map(|position| Alignment {
+                    position,
+                    fuel: aligner.fuel(crabs, position),
+                })
+                .min_by_key(|a| (a.fuel, a.position))
+        };
+
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            let crabs = (0..1 + rng.below(20))
+                .map(|_| Crab {
+                    position: rng.below(50) as i32,
+                    weight: rng.below(4) as u32 + 1,
+                })
+                .collect::<Vec<_>>();
+            let excluded = (0..rng.below(20))
+                .map(|_| rng.below(50) as i32)
+                .collect::<Vec<_>>();
+
+            for aligner in [
+                CrabAligner::linear(),
+                CrabAligner::triangular(),
+                CrabAligner::quadratic(),
+            ] {
+                let aligner = aligner.excluding(excluded.iter().cloned());
+                assert_eq!(aligner.align(&crabs), brute_force(&aligner, &crabs));
+            }
+
+            let median = weighted_median(&crabs).unwrap();
+            assert_eq!(
+                CrabAligner::linear().align(&crabs).unwrap().fuel,
+                CrabAligner::linear().fuel(&crabs, median)
+            );
+        }
+    }
+
+    #[test]
+    fn test_best_position_at_the_edge() {
+        // The old search stopped one short of the furthest crab.
+        assert_eq!(
+            CrabAligner::triangular().align(&crabs(&[9, 10, 10])),
+            Some(Alignment {
+                position: 10,
+                fuel: 1
+            })
+        );
+        assert_eq!(
+            CrabAligner::triangular()
+                .align(&crabs(&[5]))
+                .map(|a| a.fuel),
+            Some(0)
+        );
+        assert_eq!(
+            CrabAligner::new(|d| d * d * d)
+                .align(&crabs(&[0, 10, 10, 10, 10]))
+                .map(|a| a.fuel),
+            Some(451)
+        );
+        assert_eq!(CrabAligner::linear().align(&[]), None);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let input = parse_input_day7("0:2,4,10:2").unwrap();
+
+        assert_eq!(weighted_median(&input), Some(4));
+        assert_eq!(
+            CrabAligner::linear().align(&input),
+            Some(Alignment {
+                position: 4,
+                fuel: 20
+            })
+        );
+        assert_eq!(
+            CrabAligner::linear().excluding([3, 4, 5]).align(&input),
+            Some(Alignment {
+                position: 2,
+                fuel: 22
+            })
+        );
+        assert!(parse_input_day7("1:x").is_err());
+        assert!(parse_input_day7("1:-2").is_err());
     }
 }
@@ -1,112 +1,327 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-struct Decoder {
-    dictionary: HashMap<String, usize>,
+const SEGMENTS: usize = 7;
+const ALL_SEGMENTS: u8 = (1 << SEGMENTS) - 1;
+
+/// The wires or segments `a` to `g` of `s` as a bitmask, `None` for any other letter.
+fn mask(s: &str) -> Option<u8> {
+    s.chars().try_fold(0, |mask, c| match c {
+        'a'..='g' => Some(mask | 1 << (c as u8 - b'a')),
+        _ => None,
+    })
 }
 
-impl Decoder {
-    fn new(signal_patterns: Vec<String>) -> Self {
-        Decoder {
-            dictionary: Self::decode_signal_patterns(signal_patterns),
+/// The segments lit for every digit.
+#[derive(Debug, Clone)]
+pub struct Font {
+    digits: [u8; 10],
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font::new([
+            "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg",
+        ])
+    }
+}
+
+impl Font {
+    /// Builds a font from the segments of the digits 0 to 9, named as in the puzzle:
+    /// `a` top, `b` top left, `c` top right, `d` middle, `e` bottom left, `f` bottom
+    /// right and `g` bottom.
+    pub fn new(digits: [&str; 10]) -> Self {
+        let digits = digits.map(|d| mask(d).expect("segments go from a to g"));
+
+        assert!(
+            digits.iter().all_unique(),
+            "every digit needs its own segments"
+        );
+
+        Font { digits }
+    }
+
+    fn digit(&self, segments: u8) -> Option<usize> {
+        self.digits.iter().position(|&d| d == segments)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DecodeError {
+    /// No wiring turns every pattern into a digit.
+    Contradictory,
+    /// Several wirings fit the patterns and read the output differently.
+    Ambiguous,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Contradictory => write!(f, "no wiring matches the patterns"),
+            DecodeError::Ambiguous => write!(f, "several wirings match the patterns"),
         }
     }
+}
 
-    fn decode(&self, sequence: &str) -> Option<usize> {
-        self.dictionary
-            .get(&sequence.chars().sorted().collect::<String>())
-            .cloned()
+impl Error for DecodeError {}
+
+/// Every wiring, the segment each wire is plugged into, that turns all the observed
+/// patterns into digits of the font.
+pub struct Decoder {
+    font: Font,
+    wirings: Vec<[u8; SEGMENTS]>,
+}
+
+impl Decoder {
+    pub fn new(signal_patterns: &[String]) -> Result<Self, DecodeError> {
+        Self::with_font(signal_patterns, Font::default())
+    }
+
+    /// Solves the wiring, the patterns may be incomplete or repeat themselves.
+    pub fn with_font(signal_patterns: &[String], font: Font) -> Result<Self, DecodeError> {
+        let patterns = signal_patterns
+            .iter()
+            .map(|p| mask(p))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(DecodeError::Contradictory)?;
+
+        let candidates = Self::candidates(&patterns, &font);
+
+        let mut wirings = Vec::new();
+        Self::search(
+            &patterns,
+            &font,
+            &candidates,
+            &mut [0; SEGMENTS],
+            0,
+            &mut wirings,
+        );
+
+        if wirings.is_empty() {
+            return Err(DecodeError::Contradictory);
+        }
+
+        Ok(Decoder { font, wirings })
     }
 
-    fn decode_signal_patterns(signal_patterns: Vec<String>) -> HashMap<String, usize> {
-        let mut result = vec![String::new(); 10];
+    /// Narrows down the segments each wire could go to. A wire lit in a pattern goes to a
+    /// segment lit in some digit of the same size, and one that isn't lit to a segment
+    /// that is off in one of them. A segment known to belong to a wire is then taken
+    /// away from the others.
+    fn candidates(patterns: &[u8], font: &Font) -> [u8; SEGMENTS] {
+        let mut candidates = [ALL_SEGMENTS; SEGMENTS];
 
-        let mut fives = Vec::new();
-        let mut sixes = Vec::new();
+        for &pattern in patterns {
+            let (lit, unlit) = font
+                .digits
+                .iter()
+                .filter(|d| d.count_ones() == pattern.count_ones())
+                .fold((0, 0), |(lit, unlit), d| {
+                    (lit | d, unlit | !d & ALL_SEGMENTS)
+                });
 
-        for p in signal_patterns {
-            match p.len() {
-                2 => result[1] = p,
-                3 => result[7] = p,
-                4 => result[4] = p,
-                7 => result[8] = p,
-                5 => fives.push(p),
-                6 => sixes.push(p),
-                _ => unreachable!(),
+            for (wire, candidate) in candidates.iter_mut().enumerate() {
+                *candidate &= if pattern & 1 << wire != 0 { lit } else { unlit };
             }
         }
 
-        for p in fives {
-            if p.intersect(&result[4]).len() == 2 {
-                result[2] = p
-            } else if p.intersect(&result[1]).len() == 2 {
-                result[3] = p
-            } else {
-                result[5] = p
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for wire in 0..SEGMENTS {
+                let known = candidates[wire];
+                if known.count_ones() != 1 {
+                    continue;
+                }
+
+                for (other, candidate) in candidates.iter_mut().enumerate() {
+                    if other != wire && *candidate & known != 0 {
+                        *candidate &= !known;
+                        changed = true;
+                    }
+                }
             }
         }
 
-        for p in sixes {
-            if p.intersect(&result[1]).len() == 1 {
-                result[6] = p
-            } else if p.intersect(&result[4]).len() == 4 {
-                result[9] = p
-            } else {
-                result[0] = p
+        candidates
+    }
+
+    fn search(
+        patterns: &[u8],
+        font: &Font,
+        candidates: &[u8; SEGMENTS],
+        wiring: &mut [u8; SEGMENTS],
+        wire: usize,
+        wirings: &mut Vec<[u8; SEGMENTS]>,
+    ) {
+        if wire == SEGMENTS {
+            if patterns
+                .iter()
+                .all(|&p| font.digit(Self::light(wiring, p)).is_some())
+            {
+                wirings.push(*wiring);
             }
+
+            return;
         }
 
-        result
+        let used = wiring[..wire].iter().fold(0, |used, s| used | s);
+
+        for segment in (0..SEGMENTS).map(|s| 1 << s) {
+            if candidates[wire] & segment != 0 && used & segment == 0 {
+                wiring[wire] = segment;
+                Self::search(patterns, font, candidates, wiring, wire + 1, wirings);
+            }
+        }
+    }
+
+    /// The segments lit by the wires of `pattern`.
+    fn light(wiring: &[u8; SEGMENTS], pattern: u8) -> u8 {
+        (0..SEGMENTS)
+            .filter(|wire| pattern & 1 << wire != 0)
+            .fold(0, |segments, wire| segments | wiring[wire])
+    }
+
+    /// The segment each wire `a` to `g` goes to, once the patterns leave a single wiring.
+    pub fn wiring(&self) -> Option<String> {
+        match self.wirings.as_slice() {
+            [wiring] => Some(
+                wiring
+                    .iter()
+                    .map(|s| (b'a' + s.trailing_zeros() as u8) as char)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Reads a sequence, which only has to agree between the wirings that make it a digit.
+    pub fn decode(&self, sequence: &str) -> Result<usize, DecodeError> {
+        let pattern = mask(sequence).ok_or(DecodeError::Contradictory)?;
+
+        let digits = self
+            .wirings
             .iter()
-            .map(|p| p.chars().sorted().collect::<String>())
-            .zip((0..result.len()).into_iter())
-            .collect()
+            .filter_map(|w| self.font.digit(Self::light(w, pattern)))
+            .unique()
+            .collect_vec();
+
+        match digits.as_slice() {
+            [digit] => Ok(*digit),
+            [] => Err(DecodeError::Contradictory),
+            _ => Err(DecodeError::Ambiguous),
+        }
     }
 }
 
-trait Intersection<T> {
-    fn intersect(&self, other: &T) -> T;
+/// Lights up digits through a wiring, the inverse of a [`Decoder`].
+pub struct Encoder {
+    font: Font,
+    /// The wire plugged into every segment.
+    wires: [u8; SEGMENTS],
 }
 
-impl Intersection<String> for String {
-    fn intersect(&self, other: &String) -> String {
-        self.chars()
-            .collect::<HashSet<_>>()
-            .intersection(&other.chars().collect::<HashSet<_>>())
+impl Encoder {
+    /// `wiring` gives the segment of each wire `a` to `g`, like [`Decoder::wiring`].
+    pub fn new(font: Font, wiring: &str) -> Option<Self> {
+        let mut wires = [0; SEGMENTS];
+
+        for (wire, segment) in wiring.chars().enumerate() {
+            let segment = mask(&segment.to_string())?.trailing_zeros() as usize;
+            if wire >= SEGMENTS || wires[segment] != 0 {
+                return None;
+            }
+            wires[segment] = 1 << wire;
+        }
+
+        wires
+            .iter()
+            .all(|&w| w != 0)
+            .then_some(Encoder { font, wires })
+    }
+
+    /// The wires lit for `digit`, in alphabetical order.
+    pub fn encode(&self, digit: usize) -> String {
+        let segments = self.font.digits[digit];
+        let wires = (0..SEGMENTS)
+            .filter(|s| segments & 1 << s != 0)
+            .fold(0, |wires, s| wires | self.wires[s]);
+
+        (0..SEGMENTS)
+            .filter(|w| wires & 1 << w != 0)
+            .map(|w| (b'a' + w as u8) as char)
             .collect()
     }
+
+    /// An entry showing all ten digits as patterns, in order, then `output`.
+    pub fn entry(&self, output: &[usize]) -> Entry {
+        Entry {
+            patterns: (0..10).map(|d| self.encode(d)).collect(),
+            output: output.iter().map(|&d| self.encode(d)).collect(),
+        }
+    }
 }
 
 #[derive(Debug)]
-struct Entry {
-    patterns: Vec<String>,
-    output: Vec<String>,
+pub struct Entry {
+    pub patterns: Vec<String>,
+    pub output: Vec<String>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} | {}", self.patterns.join(" "), self.output.join(" "))
+    }
+}
+
+impl Entry {
+    /// Reads the output, using the output digits as extra patterns for the wiring.
+    pub fn decode(&self, font: &Font) -> Result<usize, DecodeError> {
+        let patterns = self
+            .patterns
+            .iter()
+            .chain(self.output.iter())
+            .cloned()
+            .collect_vec();
+        let decoder = Decoder::with_font(&patterns, font.clone())?;
+
+        self.output
+            .iter()
+            .try_fold(0, |sum, o| Ok(sum * 10 + decoder.decode(o)?))
+    }
 }
 
 #[aoc_generator(day8)]
-fn parse_input_day8(input: &str) -> Vec<Entry> {
+fn parse_input_day8(input: &str) -> Result<Vec<Entry>, ParseError> {
+    let source = Source::new(8, input);
+
     input
         .lines()
         .map(|l| {
-            let mut parts = l.split(" | ");
-
-            Entry {
-                patterns: parts
-                    .next()
-                    .unwrap()
-                    .split_whitespace()
-                    .map(str::to_string)
-                    .collect_vec(),
-                output: parts
-                    .next()
-                    .unwrap()
-                    .split_whitespace()
-                    .map(str::to_string)
-                    .collect_vec(),
-            }
+            let (patterns, output) = source.split_once(l, " | ")?;
+
+            let signals = |part: &str| {
+                part.split_whitespace()
+                    .map(|s| {
+                        if s.chars().all(|c| ('a'..='g').contains(&c)) {
+                            Ok(s.to_string())
+                        } else {
+                            Err(source.unexpected(s))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+
+            Ok(Entry {
+                patterns: signals(patterns)?,
+                output: signals(output)?,
+            })
         })
-        .collect_vec()
+        .collect()
 }
 
 #[aoc(day8, part1)]
@@ -119,29 +334,22 @@ fn day8_part1(input: &[Entry]) -> usize {
 
     input
         .iter()
-        .map(|e| e.output.clone())
-        .flatten()
+        .flat_map(|e| e.output.clone())
         .filter(|o| index[o.len()] == 1)
         .count()
 }
 
 #[aoc(day8, part2)]
-fn day8_part2(input: &[Entry]) -> usize {
-    input
-        .iter()
-        .map(|e| {
-            let decoder = Decoder::new(e.patterns.to_vec());
-            e.output
-                .iter()
-                .filter_map(|o| decoder.decode(o))
-                .fold(0, |sum, o| sum * 10 + o)
-        })
-        .sum::<usize>()
+fn day8_part2(input: &[Entry]) -> Option<usize> {
+    let font = Font::default();
+
+    input.iter().map(|e| e.decode(&font).ok()).sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Rng;
 
     #[test]
     fn test_part1() {
@@ -157,7 +365,7 @@ bdfegc cbegaf gecbf dfcage bdacg ed bedf ced adcbefg gebcd | ed bcgafe cdgba cbg
 egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg | gbdfcae bgc cg cgb
 gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce";
 
-        assert_eq!(day8_part1(&parse_input_day8(input)), 26);
+        assert_eq!(day8_part1(&parse_input_day8(input).unwrap()), 26);
     }
 
     #[test]
@@ -174,7 +382,128 @@ bdfegc cbegaf gecbf dfcage bdacg ed bedf ced adcbefg gebcd | ed bcgafe cdgba cbg
 egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg | gbdfcae bgc cg cgb
 gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce";
 
-        assert_eq!(day8_part2(&parse_input_day8(input)), 61229);
+        assert_eq!(day8_part2(&parse_input_day8(input).unwrap()), Some(61229));
+    }
+
+    fn patterns(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_wiring() {
+        let decoder = Decoder::new(&patterns(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab",
+        ))
+        .unwrap();
+
+        assert_eq!(decoder.wiring(), Some("cfgabde".to_string()));
+        assert_eq!(decoder.decode("cdfeb"), Ok(5));
+        assert_eq!(decoder.decode("fcadb"), Ok(3));
+    }
+
+    #[test]
+    fn test_inconsistent_patterns() {
+        assert_eq!(
+            Decoder::new(&patterns("ab cd")).err(),
+            Some(DecodeError::Contradictory)
+        );
+        assert_eq!(
+            Decoder::new(&patterns("a")).err(),
+            Some(DecodeError::Contradictory)
+        );
+        assert_eq!(
+            Decoder::new(&patterns("ab x")).err(),
+            Some(DecodeError::Contradictory)
+        );
+
+        let decoder = Decoder::new(&patterns("ab")).unwrap();
+        assert_eq!(decoder.wiring(), None);
+        assert_eq!(decoder.decode("ba"), Ok(1));
+        assert_eq!(decoder.decode("abc"), Ok(7));
+        assert_eq!(decoder.decode("abcde"), Ok(3));
+        assert_eq!(decoder.decode("acdef"), Err(DecodeError::Ambiguous));
+        assert_eq!(decoder.decode("cd"), Err(DecodeError::Contradictory));
+    }
+
+    #[test]
+    fn test_missing_patterns() {
+        let entry = parse_input_day8(
+            "cdfbe gcdfa fbcad cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )
+        .unwrap()
+        .remove(0);
+
+        assert_eq!(entry.decode(&Font::default()), Ok(5353));
+    }
+
+    #[test]
+    fn test_alternative_font() {
+        // A 6 without its top, a 7 with its top left and a 9 without its bottom.
+        let font = Font::new([
+            "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "bdefg", "abcf", "abcdefg", "abcdf",
+        ]);
+
+        // Wires a to g go to segments c, f, g, a, b, d and e.
+        let scrambled = Encoder::new(font.clone(), "cfgabde")
+            .unwrap()
+            .entry(&[])
+            .patterns;
+
+        let decoder = Decoder::with_font(&scrambled, font.clone()).unwrap();
+        assert_eq!(decoder.wiring(), Some("cfgabde".to_string()));
+        for (digit, pattern) in scrambled.iter().enumerate() {
+            assert_eq!(decoder.decode(pattern), Ok(digit));
+        }
+
+        assert!(Decoder::new(&scrambled).is_err());
+    }
+
+    #[test]
+    fn test_encoder() {
+        let encoder = Encoder::new(Font::default(), "cfgabde").unwrap();
+
+        assert_eq!(
+            encoder.entry(&[5, 3, 5, 3]).to_string(),
+            "abcdeg ab acdfg abcdf abef bcdef bcdefg abd abcdefg abcdef | bcdef abcdf bcdef abcdf"
+        );
+        assert!(Encoder::new(Font::default(), "cfgabd").is_none());
+        assert!(Encoder::new(Font::default(), "cfgabdd").is_none());
+        assert!(Encoder::new(Font::default(), "cfgabdex").is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Rng::new(8);
+        let alternative = Font::new([
+            "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "bdefg", "abcf", "abcdefg", "abcdf",
+        ]);
+
+        for font in [Font::default(), alternative] {
+            for _ in 0..1000 {
+                let mut wiring = "abcdefg".chars().collect_vec();
+                rng.shuffle(&mut wiring);
+                let wiring = wiring.into_iter().collect::<String>();
+
+                let digits = (0..4).map(|_| rng.below(10) as usize).collect_vec();
+
+                let mut entry = Encoder::new(font.clone(), &wiring).unwrap().entry(&digits);
+                rng.shuffle(&mut entry.patterns);
+                for pattern in entry.patterns.iter_mut().chain(entry.output.iter_mut()) {
+                    let mut wires = pattern.chars().collect_vec();
+                    rng.shuffle(&mut wires);
+                    *pattern = wires.into_iter().collect();
+                }
+
+                let entry = parse_input_day8(&entry.to_string()).unwrap().remove(0);
+                let decoder = Decoder::with_font(&entry.patterns, font.clone()).unwrap();
+
+                assert_eq!(decoder.wiring(), Some(wiring));
+                assert_eq!(
+                    entry.decode(&font),
+                    Ok(digits.iter().fold(0, |n, d| n * 10 + d))
+                );
+            }
+        }
     }
 }
 
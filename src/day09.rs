@@ -1,227 +1,235 @@
+use crate::grid::{Grid, Point};
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::ops::{Index, IndexMut};
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-struct Point {
-    x: usize,
-    y: usize,
+/// A disjoint-set forest with union by rank and path compression.
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
-struct Board<T: Clone + Copy> {
-    width: usize,
-    height: usize,
-    inner: Vec<T>,
-}
-
-impl<T: Copy> Board<T> {
-    fn new_from_matrix(mat: Vec<Vec<T>>) -> Self {
-        Self {
-            width: mat[0].len(),
-            height: mat.len(),
-            inner: mat.iter().cloned().flatten().collect_vec(),
+impl UnionFind {
+    pub fn new(size: usize) -> Self {
+        UnionFind {
+            parent: (0..size).collect_vec(),
+            rank: vec![0; size],
         }
     }
 
-    fn adjacent_points(&self, index: Point) -> Vec<Point> {
-        let mut result = Vec::with_capacity(4);
-
-        if index.x < self.width - 1 {
-            result.push(Point {
-                x: index.x + 1,
-                y: index.y,
-            })
+    /// Merges the sets of `p1` and `p2`, returning whether they were apart.
+    pub fn union(&mut self, p1: usize, p2: usize) -> bool {
+        let (p1, p2) = (self.find(p1), self.find(p2));
+        if p1 == p2 {
+            return false;
         }
 
-        if index.y < self.height - 1 {
-            result.push(Point {
-                x: index.x,
-                y: index.y + 1,
-            })
+        let (child, root) = if self.rank[p1] < self.rank[p2] {
+            (p1, p2)
+        } else {
+            (p2, p1)
+        };
+
+        self.parent[child] = root;
+        if self.rank[child] == self.rank[root] {
+            self.rank[root] += 1;
         }
 
-        if index.x > 0 {
-            result.push(Point {
-                x: index.x - 1,
-                y: index.y,
-            })
+        true
+    }
+
+    pub fn find(&mut self, p: usize) -> usize {
+        let mut root = p;
+        while root != self.parent[root] {
+            root = self.parent[root];
         }
 
-        if index.y > 0 {
-            result.push(Point {
-                x: index.x,
-                y: index.y - 1,
-            })
+        let mut p = p;
+        while p != root {
+            p = std::mem::replace(&mut self.parent[p], root);
         }
 
-        result
+        root
     }
 }
 
-impl<T: Copy> Index<Point> for Board<T> {
-    type Output = T;
+#[derive(Debug, Eq, PartialEq)]
+pub struct Basin {
+    pub id: usize,
+    /// The lowest cell, the first one in reading order on a tie.
+    pub low_point: Point,
+    /// The cells in reading order.
+    pub cells: Vec<Point>,
+}
 
-    fn index(&self, index: Point) -> &Self::Output {
-        let point_to_index = |p: Point| p.y * self.width + p.x;
-        &self.inner[point_to_index(index)]
+impl Basin {
+    pub fn size(&self) -> usize {
+        self.cells.len()
     }
 }
 
-impl<T: Copy> IndexMut<Point> for Board<T> {
-    fn index_mut(&mut self, index: Point) -> &mut Self::Output {
-        let point_to_index = |p: Point| p.y * self.width + p.x;
-        self.inner.get_mut(point_to_index(index)).unwrap()
-    }
+/// Where water goes from a cell.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Flow {
+    /// To its lowest neighbour, if that one is lower.
+    #[default]
+    Steepest,
+    /// To every lower neighbour.
+    AnyLower,
 }
 
-struct BoardIterator<T: Clone + Copy> {
-    board: Board<T>,
-    last_index: usize,
+/// How a heightmap splits into basins.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Watershed {
+    /// Cells at least this high are walls that belong to no basin.
+    pub wall: usize,
+    pub flow: Flow,
 }
 
-impl<T: Copy> BoardIterator<T> {
-    fn new(board: Board<T>) -> Self {
-        BoardIterator {
-            board,
-            last_index: 0,
+impl Default for Watershed {
+    fn default() -> Self {
+        Watershed {
+            wall: 9,
+            flow: Flow::Steepest,
         }
     }
 }
 
-impl<T: Copy> Iterator for BoardIterator<T> {
-    type Item = (Point, T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.last_index >= self.board.height * self.board.width {
-            None
-        } else {
-            let index_to_point = |i: usize| Point {
-                x: i % self.board.width,
-                y: i / self.board.width,
+impl Watershed {
+    /// A basin is everything that flows to the same place.
+    pub fn basins(&self, heightmap: &Grid<usize>) -> Basins {
+        let mut uf = UnionFind::new(heightmap.len());
+        let is_wall = |p: Point| heightmap[p] >= self.wall;
+
+        for p in heightmap.points().filter(|&p| !is_wall(p)) {
+            let lower = heightmap
+                .neighbours4(p)
+                .filter(|&q| heightmap[q] < heightmap[p])
+                .collect_vec();
+
+            let targets = match self.flow {
+                Flow::Steepest => lower
+                    .into_iter()
+                    .min_by_key(|&q| heightmap[q])
+                    .into_iter()
+                    .collect(),
+                Flow::AnyLower => lower,
             };
 
-            let p = index_to_point(self.last_index);
+            for q in targets {
+                uf.union(heightmap.index_of(p), heightmap.index_of(q));
+            }
+        }
 
-            self.last_index += 1;
+        let mut cells: HashMap<usize, Vec<Point>> = HashMap::new();
+        for p in heightmap.points().filter(|&p| !is_wall(p)) {
+            cells
+                .entry(uf.find(heightmap.index_of(p)))
+                .or_default()
+                .push(p);
+        }
+
+        let mut basins = cells
+            .into_values()
+            .map(|cells| Basin {
+                id: 0,
+                low_point: *cells
+                    .iter()
+                    .min_by_key(|&&p| (heightmap[p], p.y, p.x))
+                    .unwrap(),
+                cells,
+            })
+            .collect_vec();
+        basins.sort_unstable_by_key(|b| (b.low_point.y, b.low_point.x));
 
-            Some((p, self.board[p]))
+        let mut labels = Grid::filled(heightmap.width(), heightmap.height(), None);
+        for (id, basin) in basins.iter_mut().enumerate() {
+            basin.id = id;
+            basin.cells.iter().for_each(|&p| labels[p] = Some(id));
         }
+
+        Basins { basins, labels }
     }
 }
 
-impl<T: Copy> IntoIterator for Board<T> {
-    type Item = (Point, T);
-    type IntoIter = BoardIterator<T>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        BoardIterator::new(self)
-    }
+/// A wall cell between basins, which would merge if it were lowered.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Saddle {
+    pub point: Point,
+    /// The ids of the neighbouring basins, sorted.
+    pub basins: Vec<usize>,
 }
 
-struct UnionFind {
-    mask: Vec<usize>,
+/// The basins of a heightmap, numbered in the reading order of their low points.
+pub struct Basins {
+    basins: Vec<Basin>,
+    labels: Grid<Option<usize>>,
 }
 
-impl UnionFind {
-    fn new(size: usize) -> Self {
-        UnionFind {
-            mask: (0..size).into_iter().collect_vec(),
-        }
+impl Basins {
+    /// The basins with the default [`Watershed`].
+    pub fn new(heightmap: &Grid<usize>) -> Self {
+        Watershed::default().basins(heightmap)
     }
 
-    fn union(&mut self, p1: usize, p2: usize) {
-        let p1 = self.find(p1);
-        let p2 = self.find(p2);
-        self.mask[p1] = self.mask[p2];
-
-        for p in 0..self.mask.len() {
-            if self.mask[p] == p1 {
-                self.mask[p] = self.mask[p2];
-            }
-        }
+    pub fn basins(&self) -> &[Basin] {
+        &self.basins
     }
 
-    fn find(&mut self, mut p: usize) -> usize {
-        while p != self.mask[p] {
-            p = self.mask[p];
-        }
+    /// The basin `p` belongs to, `None` for walls.
+    pub fn basin_of(&self, p: Point) -> Option<&Basin> {
+        self.labels
+            .get(p)
+            .cloned()
+            .flatten()
+            .map(|id| &self.basins[id])
+    }
 
-        p
+    /// The wall cells touching at least two basins, in reading order.
+    pub fn saddles(&self) -> Vec<Saddle> {
+        self.labels
+            .points()
+            .filter(|&p| self.labels[p].is_none())
+            .map(|p| Saddle {
+                point: p,
+                basins: self
+                    .labels
+                    .neighbours4(p)
+                    .filter_map(|q| self.labels[q])
+                    .sorted()
+                    .dedup()
+                    .collect(),
+            })
+            .filter(|s| s.basins.len() > 1)
+            .collect()
     }
 }
 
 #[aoc_generator(day9)]
-fn parse_input_day9(input: &str) -> Board<usize> {
-    Board::new_from_matrix(
-        input
-            .lines()
-            .map(|l| {
-                l.chars()
-                    .map(|c| c.to_digit(10).unwrap() as usize)
-                    .collect_vec()
-            })
-            .collect_vec(),
-    )
+fn parse_input_day9(input: &str) -> Result<Grid<usize>, ParseError> {
+    Grid::parse_digits(&Source::new(9, input), input)
 }
 
 #[aoc(day9, part1)]
-fn day9_part1(input: &Board<usize>) -> usize {
+fn day9_part1(input: &Grid<usize>) -> usize {
     input
-        .clone()
-        .into_iter()
-        .filter_map(|(p, _)| {
-            if input
-                .adjacent_points(p)
-                .into_iter()
-                .all(|adj| input[p] < input[adj])
-            {
-                Some(p)
-            } else {
-                None
-            }
-        })
+        .points()
+        .filter(|&p| input.neighbours4(p).all(|adj| input[p] < input[adj]))
         .map(|p| 1 + input[p])
         .sum()
 }
 
 #[aoc(day9, part2)]
-fn day9_part2(input: &Board<usize>) -> usize {
-    let point_to_index = |p: Point| p.y * input.width + p.x;
-
-    let mut uf = UnionFind::new(input.width * input.height);
-
-    input
-        .clone()
-        .into_iter()
-        .filter_map(|(p, _)| {
-            let mut lowest_of_adjacent = p;
-
-            for adj in input.adjacent_points(p) {
-                if input[adj] < input[lowest_of_adjacent] {
-                    lowest_of_adjacent = adj;
-                }
-            }
-
-            if input[p] == 9 {
-                return None;
-            }
-
-            if p == lowest_of_adjacent {
-                return None;
-            }
-
-            Some((p, lowest_of_adjacent))
-        })
-        .for_each(|(p, adj)| uf.union(point_to_index(p), point_to_index(adj)));
-
-    let mut counts: HashMap<usize, usize> = HashMap::new();
-    uf.mask
+fn day9_part2(input: &Grid<usize>) -> usize {
+    Basins::new(input)
+        .basins()
         .iter()
-        .for_each(|p| *counts.entry(*p).or_default() += 1);
-
-    counts.values().into_iter().sorted().rev().take(3).product()
+        .map(|b| b.size())
+        .sorted()
+        .rev()
+        .take(3)
+        .product()
 }
 
 #[cfg(test)]
@@ -236,7 +244,7 @@ mod tests {
 8767896789
 9899965678";
 
-        assert_eq!(day9_part1(&parse_input_day9(input)), 15);
+        assert_eq!(day9_part1(&parse_input_day9(input).unwrap()), 15);
     }
 
     #[test]
@@ -247,6 +255,121 @@ mod tests {
 8767896789
 9899965678";
 
-        assert_eq!(day9_part2(&parse_input_day9(input)), 1134);
+        assert_eq!(day9_part2(&parse_input_day9(input).unwrap()), 1134);
+    }
+
+    #[test]
+    fn test_union_find() {
+        let mut uf = UnionFind::new(6);
+
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(1, 3));
+        assert!(!uf.union(0, 2));
+        assert_eq!(uf.find(0), uf.find(3));
+        assert_ne!(uf.find(0), uf.find(4));
+
+        // Finding compresses the path straight to the root.
+        let root = uf.find(3);
+        assert_eq!(uf.parent[3], root);
+    }
+
+    #[test]
+    fn test_basins() {
+        let input = parse_input_day9(
+            "2199943210
+3987894921
+9856789892
+8767896789
+9899965678",
+        )
+        .unwrap();
+        let basins = Basins::new(&input);
+
+        assert_eq!(
+            basins
+                .basins()
+                .iter()
+                .map(|b| (b.id, b.low_point, b.size()))
+                .collect_vec(),
+            vec![
+                (0, Point::new(1, 0), 3),
+                (1, Point::new(9, 0), 9),
+                (2, Point::new(2, 2), 14),
+                (3, Point::new(6, 4), 9),
+            ]
+        );
+        assert_eq!(basins.basin_of(Point::new(0, 1)).map(|b| b.id), Some(0));
+        assert_eq!(basins.basin_of(Point::new(2, 0)), None);
+        assert_eq!(basins.basin_of(Point::new(5, 4)).map(|b| b.id), Some(3));
+    }
+
+    #[test]
+    fn test_watershed() {
+        let input = parse_input_day9(
+            "2199943210
+3987894921
+9856789892
+8767896789
+9899965678",
+        )
+        .unwrap();
+
+        let basins = Basins::new(&input);
+        let saddles = basins.saddles();
+        assert_eq!(
+            saddles.first(),
+            Some(&Saddle {
+                point: Point::new(2, 0),
+                basins: vec![0, 2],
+            })
+        );
+        assert!(saddles.iter().all(|s| input[s.point] == 9));
+
+        // Without walls the whole map drains into the lowest point of each valley.
+        let open = Watershed {
+            wall: 10,
+            flow: Flow::Steepest,
+        };
+        assert_eq!(open.basins(&input).saddles(), vec![]);
+        assert_eq!(
+            open.basins(&input)
+                .basins()
+                .iter()
+                .map(|b| b.size())
+                .sum::<usize>(),
+            input.len()
+        );
+
+        // Lower walls split the map into more, smaller pieces.
+        let low_walls = Watershed {
+            wall: 7,
+            flow: Flow::AnyLower,
+        };
+        let basins = low_walls.basins(&input);
+        assert!(basins
+            .basins()
+            .iter()
+            .all(|b| b.cells.iter().all(|&p| input[p] < 7)));
+        assert_eq!(basins.basin_of(Point::new(2, 2)).map(|b| b.size()), Some(3));
+    }
+
+    #[test]
+    fn test_flow() {
+        // The 5 drains only to the 1 when following the steepest slope.
+        let input = parse_input_day9("153").unwrap();
+
+        let steepest = Watershed::default().basins(&input);
+        assert_eq!(
+            steepest.basins().iter().map(|b| b.size()).collect_vec(),
+            vec![2, 1]
+        );
+
+        let any_lower = Watershed {
+            flow: Flow::AnyLower,
+            ..Default::default()
+        }
+        .basins(&input);
+        assert_eq!(any_lower.basins().len(), 1);
     }
 }
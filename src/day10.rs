@@ -1,125 +1,186 @@
-use crate::day10::State::{Corrupted, Incomplete, Valid};
-use crate::day10::Token::{
-    LeftAngle, LeftCurly, LeftParen, LeftSquare, RightAngle, RightCurly, RightParen, RightSquare,
-};
-use aoc_runner_derive::aoc;
+use crate::day10::State::{Corrupted, Incomplete, UnexpectedCloser, Valid};
+use crate::parse::{ParseError, Source};
+use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use std::ops::Not;
-
-#[derive(Eq, PartialEq, Clone, Copy)]
-enum Token {
-    LeftParen,
-    RightParen,
-    LeftSquare,
-    RightSquare,
-    LeftCurly,
-    RightCurly,
-    LeftAngle,
-    RightAngle,
-}
-
-impl Token {
-    fn score_corrupted(self) -> usize {
-        match self {
-            RightParen => 3,
-            RightSquare => 57,
-            RightCurly => 1197,
-            RightAngle => 25137,
-            _ => unreachable!(),
-        }
-    }
 
-    fn score_incomplete(self) -> usize {
-        match self {
-            RightParen => 1,
-            RightSquare => 2,
-            RightCurly => 3,
-            RightAngle => 4,
-            _ => unreachable!(),
-        }
+/// An opening and closing delimiter with their scores.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BracketPair {
+    pub open: char,
+    pub close: char,
+    /// Scored when `close` is the first illegal character of a line.
+    pub corrupted_score: usize,
+    /// Scored for every `close` needed to complete a line.
+    pub incomplete_score: usize,
+}
+
+/// The delimiters a [`Parser`] checks, the puzzle's four kinds by default.
+#[derive(Debug, Clone)]
+pub struct BracketSet {
+    pairs: Vec<BracketPair>,
+    /// What the completion score is multiplied by before adding each closer.
+    completion_base: usize,
+}
+
+impl Default for BracketSet {
+    fn default() -> Self {
+        let pair = |open, close, corrupted_score, incomplete_score| BracketPair {
+            open,
+            close,
+            corrupted_score,
+            incomplete_score,
+        };
+
+        BracketSet::new(
+            vec![
+                pair('(', ')', 3, 1),
+                pair('[', ']', 57, 2),
+                pair('{', '}', 1197, 3),
+                pair('<', '>', 25137, 4),
+            ],
+            5,
+        )
     }
 }
 
-impl Not for Token {
-    type Output = Token;
+impl BracketSet {
+    pub fn new(pairs: Vec<BracketPair>, completion_base: usize) -> Self {
+        assert!(
+            pairs.iter().flat_map(|p| [p.open, p.close]).all_unique(),
+            "every delimiter needs its own character"
+        );
 
-    fn not(self) -> Self::Output {
-        match self {
-            LeftParen => RightParen,
-            LeftSquare => RightSquare,
-            LeftCurly => RightCurly,
-            LeftAngle => RightAngle,
-            RightParen => LeftParen,
-            RightSquare => LeftSquare,
-            RightCurly => LeftCurly,
-            RightAngle => LeftAngle,
+        BracketSet {
+            pairs,
+            completion_base,
         }
     }
+
+    fn contains(&self, c: char) -> bool {
+        self.pairs.iter().any(|p| p.open == c || p.close == c)
+    }
+
+    fn opened_by(&self, c: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|p| p.open == c)
+    }
+
+    fn closed_by(&self, c: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|p| p.close == c)
+    }
+
+    /// The closer matching the opener `c`.
+    fn closer(&self, c: char) -> char {
+        self.opened_by(c).map(|p| p.close).unwrap()
+    }
+
+    pub fn score_corrupted(&self, found: &Bracket) -> usize {
+        self.closed_by(found.character)
+            .map_or(0, |p| p.corrupted_score)
+    }
+
+    /// Scores the closers completing `open`, innermost first.
+    pub fn score_incomplete(&self, open: &[Bracket]) -> usize {
+        open.iter().rev().fold(0, |score, b| {
+            score * self.completion_base
+                + self
+                    .opened_by(b.character)
+                    .map_or(0, |p| p.incomplete_score)
+        })
+    }
 }
 
-impl From<char> for Token {
-    fn from(c: char) -> Self {
-        match c {
-            '(' => LeftParen,
-            ')' => RightParen,
-            '[' => LeftSquare,
-            ']' => RightSquare,
-            '{' => LeftCurly,
-            '}' => RightCurly,
-            '<' => LeftAngle,
-            '>' => RightAngle,
-            _ => unreachable!(),
-        }
+/// A bracket and its column in the line, counting from 1.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Bracket {
+    pub character: char,
+    pub column: usize,
+}
+
+pub struct SourceLine {
+    text: String,
+}
+
+impl SourceLine {
+    fn parse(source: &Source, l: &str) -> Result<Self, ParseError> {
+        let brackets = BracketSet::default();
+        source.chars(l, |c| brackets.contains(c).then_some(c))?;
+
+        Ok(SourceLine {
+            text: l.to_string(),
+        })
     }
 }
 
-struct SourceLine {
-    chunk: Vec<Token>,
+#[derive(Debug, Eq, PartialEq)]
+pub enum State {
+    Valid,
+    /// The brackets left open, innermost last.
+    Incomplete(Vec<Bracket>),
+    /// `found` doesn't close `opener`.
+    Corrupted {
+        found: Bracket,
+        opener: Bracket,
+    },
+    /// `found` closes a bracket that was never opened.
+    UnexpectedCloser(Bracket),
 }
 
-impl From<&str> for SourceLine {
-    fn from(l: &str) -> Self {
-        SourceLine {
-            chunk: l.chars().map(Token::from).collect_vec(),
+impl State {
+    /// The first character that can't be there, if any.
+    fn illegal(&self) -> Option<Bracket> {
+        match self {
+            Corrupted { found, .. } | UnexpectedCloser(found) => Some(*found),
+            Valid | Incomplete(_) => None,
         }
     }
 }
 
-enum State {
-    Valid,
-    Incomplete(Vec<Token>),
-    Corrupted(Token),
+/// The cheapest repairs of every slice `brackets[i..j]` of a line's brackets.
+struct Plan {
+    /// `edits[i][j]` is the number of edits balancing the slice.
+    edits: Vec<Vec<usize>>,
+    /// `partners[i][j]` is the bracket `i` gets paired with, `None` when it is deleted or
+    /// closed by an inserted bracket instead.
+    partners: Vec<Vec<Option<usize>>>,
 }
 
-struct Parser {}
+#[derive(Default)]
+pub struct Parser {
+    brackets: BracketSet,
+}
 
 impl Parser {
-    fn parse(&self, lines: Vec<SourceLine>) -> Vec<State> {
-        let mut states = Vec::with_capacity(lines.len());
-
-        for line in lines {
-            states.push(self.parse_line(line))
-        }
+    pub fn new(brackets: BracketSet) -> Self {
+        Parser { brackets }
+    }
 
-        states
+    fn parse(&self, lines: &[SourceLine]) -> Vec<State> {
+        lines.iter().map(|l| self.check(&l.text)).collect()
     }
 
-    fn parse_line(&self, line: SourceLine) -> State {
-        let mut stack = Vec::with_capacity(line.chunk.len());
+    /// Checks the brackets of `text`, skipping any other character.
+    pub fn check(&self, text: &str) -> State {
+        let mut stack = Vec::new();
 
-        for token in line.chunk {
-            match token {
-                LeftAngle | LeftCurly | LeftParen | LeftSquare => stack.push(token),
-                closing => match stack.pop() {
-                    Some(opening) => {
-                        if closing == !opening {
-                            continue;
-                        } else {
-                            return Corrupted(closing);
-                        }
-                    }
-                    None => unreachable!(),
-                },
+        for (i, character) in text.chars().enumerate() {
+            if !self.brackets.contains(character) {
+                continue;
+            }
+
+            let found = Bracket {
+                character,
+                column: i + 1,
+            };
+
+            if self.brackets.opened_by(character).is_some() {
+                stack.push(found);
+                continue;
+            }
+
+            match stack.pop() {
+                Some(opener) if self.brackets.closer(opener.character) == character => continue,
+                Some(opener) => return Corrupted { found, opener },
+                None => return UnexpectedCloser(found),
             }
         }
 
@@ -129,30 +190,147 @@ impl Parser {
             Incomplete(stack)
         }
     }
+
+    /// The fewest insertions, deletions and replacements of single characters
+    /// balancing the brackets of `text`.
+    pub fn edits(&self, text: &str) -> usize {
+        let brackets = text
+            .chars()
+            .filter(|&c| self.brackets.contains(c))
+            .collect_vec();
+
+        self.plan(&brackets).edits[0][brackets.len()]
+    }
+
+    /// Edits `text` until its brackets are balanced, with as few edits as [`Parser::edits`].
+    /// Other characters are kept, an inserted closer goes right before the next bracket
+    /// or at the end of the line.
+    pub fn repair(&self, text: &str) -> String {
+        let brackets = text
+            .chars()
+            .filter(|&c| self.brackets.contains(c))
+            .collect_vec();
+        let plan = self.plan(&brackets);
+
+        let mut kept = brackets.iter().map(|&c| Some(c)).collect_vec();
+        // `inserted[i]` goes right before the `i`th bracket, the last one at the end.
+        let mut inserted = vec![Vec::new(); brackets.len() + 1];
+        self.rebuild(0, brackets.len(), &plan, &mut kept, &mut inserted);
+
+        let mut repaired = String::new();
+        let mut i = 0;
+        for c in text.chars() {
+            if self.brackets.contains(c) {
+                repaired.extend(&inserted[i]);
+                repaired.extend(kept[i]);
+                i += 1;
+            } else {
+                repaired.push(c);
+            }
+        }
+        repaired.extend(&inserted[i]);
+
+        repaired
+    }
+
+    /// The edits needed to make `a` and `b` a matching pair: 0 when they are one, 1 when
+    /// either can be replaced to fit the other, 2 when both need replacing.
+    fn pair_cost(&self, a: char, b: char) -> usize {
+        match (self.brackets.opened_by(a), self.brackets.closed_by(b)) {
+            (Some(pair), _) if pair.close == b => 0,
+            (Some(_), _) | (_, Some(_)) => 1,
+            (None, None) => 2,
+        }
+    }
+
+    fn plan(&self, brackets: &[char]) -> Plan {
+        let n = brackets.len();
+        let mut edits = vec![vec![0; n + 1]; n + 1];
+        let mut partners = vec![vec![None; n + 1]; n + 1];
+
+        for length in 1..=n {
+            for i in 0..=n - length {
+                let j = i + length;
+
+                // Alone, a bracket takes one edit: deleting it or inserting its partner.
+                let mut best = (1 + edits[i + 1][j], None);
+                for k in i + 1..j {
+                    let cost = self.pair_cost(brackets[i], brackets[k])
+                        + edits[i + 1][k]
+                        + edits[k + 1][j];
+
+                    if cost < best.0 {
+                        best = (cost, Some(k));
+                    }
+                }
+
+                (edits[i][j], partners[i][j]) = best;
+            }
+        }
+
+        Plan { edits, partners }
+    }
+
+    fn rebuild(
+        &self,
+        i: usize,
+        j: usize,
+        plan: &Plan,
+        kept: &mut [Option<char>],
+        inserted: &mut [Vec<char>],
+    ) {
+        if i >= j {
+            return;
+        }
+
+        let Some(k) = plan.partners[i][j] else {
+            let closer = self.brackets.opened_by(kept[i].unwrap()).map(|p| p.close);
+            if closer.is_none() {
+                kept[i] = None;
+            }
+
+            self.rebuild(i + 1, j, plan, kept, inserted);
+            inserted[j].extend(closer);
+            return;
+        };
+
+        let (a, b) = (kept[i].unwrap(), kept[k].unwrap());
+        let pair = match (self.brackets.opened_by(a), self.brackets.closed_by(b)) {
+            (Some(pair), _) | (_, Some(pair)) => pair,
+            (None, None) => &self.brackets.pairs[0],
+        };
+        (kept[i], kept[k]) = (Some(pair.open), Some(pair.close));
+
+        self.rebuild(i + 1, k, plan, kept, inserted);
+        self.rebuild(k + 1, j, plan, kept, inserted);
+    }
 }
 
-fn parse_input_day10(input: &str) -> Vec<SourceLine> {
-    input.lines().map(SourceLine::from).collect_vec()
+#[aoc_generator(day10)]
+fn parse_input_day10(input: &str) -> Result<Vec<SourceLine>, ParseError> {
+    let source = Source::new(10, input);
+
+    input
+        .lines()
+        .map(|l| SourceLine::parse(&source, l))
+        .collect()
 }
 
 #[aoc(day10, part1)]
-fn day10_part1(input: &str) -> usize {
-    let lines = parse_input_day10(input);
-
-    let parser = Parser {};
+fn day10_part1(lines: &[SourceLine]) -> usize {
+    let parser = Parser::default();
 
     parser
         .parse(lines)
-        .into_iter()
-        .filter_map(|s| if let Corrupted(c) = s { Some(c) } else { None })
-        .map(|c| c.score_corrupted())
+        .iter()
+        .filter_map(|s| s.illegal())
+        .map(|b| parser.brackets.score_corrupted(&b))
         .sum()
 }
 
 #[aoc(day10, part2)]
-fn day10_part2(input: &str) -> usize {
-    let lines = parse_input_day10(input);
-    let parser = Parser {};
+fn day10_part2(lines: &[SourceLine]) -> usize {
+    let parser = Parser::default();
 
     let sorted_scores = parser
         .parse(lines)
@@ -164,12 +342,7 @@ fn day10_part2(input: &str) -> usize {
                 None
             }
         })
-        .map(|open_gates| {
-            open_gates
-                .iter()
-                .rev()
-                .fold(0, |score, c| score * 5 + (!*c).score_incomplete())
-        })
+        .map(|open_gates| parser.brackets.score_incomplete(&open_gates))
         .sorted()
         .collect_vec();
 
@@ -192,7 +365,7 @@ mod tests {
 [<(<(<(<{}))><([]([]()
 <{([([[(<>()){}]>(<<{{
 <{([{{}}[<[[[<>{}]]]>[]]";
-        assert_eq!(day10_part1(input), 26397);
+        assert_eq!(day10_part1(&parse_input_day10(input).unwrap()), 26397);
     }
 
     #[test]
@@ -207,6 +380,140 @@ mod tests {
 [<(<(<(<{}))><([]([]()
 <{([([[(<>()){}]>(<<{{
 <{([{{}}[<[[[<>{}]]]>[]]";
-        assert_eq!(day10_part2(input), 288957);
+        assert_eq!(day10_part2(&parse_input_day10(input).unwrap()), 288957);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let parser = Parser::default();
+
+        assert_eq!(
+            parser.check("{([(<{}[<>[]}>{[]{[(<()>"),
+            Corrupted {
+                found: Bracket {
+                    character: '}',
+                    column: 13
+                },
+                opener: Bracket {
+                    character: '[',
+                    column: 8
+                },
+            }
+        );
+        assert_eq!(
+            parser.check("f(x) + g(y))"),
+            UnexpectedCloser(Bracket {
+                character: ')',
+                column: 12
+            })
+        );
+        assert_eq!(
+            parser.check("if (a < b"),
+            Incomplete(vec![
+                Bracket {
+                    character: '(',
+                    column: 4
+                },
+                Bracket {
+                    character: '<',
+                    column: 7
+                },
+            ])
+        );
+        assert_eq!(parser.check("no brackets"), Valid);
+    }
+
+    /// The Levenshtein distance between `a` and `b`.
+    fn distance(a: &str, b: &str) -> usize {
+        let b = b.chars().collect_vec();
+        let mut row = (0..=b.len()).collect_vec();
+
+        for (i, ca) in a.chars().enumerate() {
+            let mut previous = row[0];
+            row[0] = i + 1;
+
+            for (j, &cb) in b.iter().enumerate() {
+                let substitution = previous + (ca != cb) as usize;
+                previous = row[j + 1];
+                row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+            }
+        }
+
+        row[b.len()]
+    }
+
+    #[test]
+    fn test_repair() {
+        let parser = Parser::default();
+
+        // Turning pairs of open brackets around is cheaper than closing all 8 of them.
+        assert_eq!(
+            parser.repair("[({(<(())[]>[[{[]{<()<>>"),
+            "[]{}<(())[]>[]{[]}<()<>>"
+        );
+        assert_eq!(parser.edits("[({(<(())[]>[[{[]{<()<>>"), 4);
+        assert_eq!(parser.edits("{([(<{}[<>[]}>{[]{[(<()>"), 5);
+        assert_eq!(parser.repair("f(x)) + [y"), "f(x) + [y]");
+        assert_eq!(parser.repair("(]"), "()");
+        assert_eq!(parser.repair("(<)"), "(<>)");
+        assert_eq!(parser.edits("(<)"), 1);
+        assert_eq!(parser.repair("if (a < b"), "if (a ) b");
+
+        for line in [
+            "{([(<{}[<>[]}>{[]{[(<()>",
+            "])}>",
+            "<{([",
+            "(]{>[)",
+            "text",
+            "([)]",
+            "x)(y",
+        ] {
+            let repaired = parser.repair(line);
+
+            assert_eq!(parser.check(&repaired), Valid);
+            assert_eq!(distance(line, &repaired), parser.edits(line));
+        }
+    }
+
+    #[test]
+    fn test_bracket_set() {
+        let pair = |open, close, corrupted_score, incomplete_score| BracketPair {
+            open,
+            close,
+            corrupted_score,
+            incomplete_score,
+        };
+        let parser = Parser::new(BracketSet::new(
+            vec![pair('«', '»', 10, 1), pair('<', '>', 20, 2)],
+            3,
+        ));
+
+        assert_eq!(parser.check("«a <b> c» (]"), Valid);
+        let state = parser.check("«<»");
+        assert_eq!(
+            state,
+            Corrupted {
+                found: Bracket {
+                    character: '»',
+                    column: 3
+                },
+                opener: Bracket {
+                    character: '<',
+                    column: 2
+                },
+            }
+        );
+        assert_eq!(
+            parser.brackets.score_corrupted(&state.illegal().unwrap()),
+            10
+        );
+
+        let Incomplete(open) = parser.check("«<«") else {
+            panic!("should be incomplete");
+        };
+        // Closing with », > and » scores 1, then 1 * 3 + 2 and then 5 * 3 + 1.
+        assert_eq!(parser.brackets.score_incomplete(&open), 16);
+        assert_eq!(parser.repair("«<«"), "«<>»");
+        assert_eq!(parser.edits("«<«"), 2);
     }
 }
@@ -1,145 +1,119 @@
+use crate::automaton::{Automaton, Cascade, Neighbourhood, Rule, Update};
+use crate::cycle::{self, History};
 use crate::day11::Octopus::{Charging, Flashing};
+use crate::grid::Grid;
+use crate::parse::{ParseError, Source};
 use ansi_term::Colour::Red;
-use aoc_runner_derive::aoc;
-use itertools::Itertools;
-use std::fmt::{Debug, Formatter};
+use aoc_runner_derive::{aoc, aoc_generator};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 enum Octopus {
     Flashing,
     Charging(u32),
 }
 
-impl From<char> for Octopus {
-    fn from(c: char) -> Self {
+impl Octopus {
+    fn from_char(c: char) -> Option<Self> {
         match c.to_digit(10) {
-            Some(0) => Flashing,
-            Some(n) => Charging(n),
-            None => unreachable!(),
+            Some(0) => Some(Flashing),
+            Some(n) => Some(Charging(n)),
+            None => None,
         }
     }
 }
 
-struct DumboOctopuses {
-    width: usize,
-    height: usize,
-    octopuses: Vec<Octopus>,
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct DumboOctopuses {
+    octopuses: Automaton<Octopus>,
 }
 
 impl DumboOctopuses {
-    fn step(&mut self) {
-        let mut should_flash = Vec::new();
-
-        for (i, o) in self.octopuses.iter_mut().enumerate() {
-            match o {
-                Charging(9) => should_flash.push(i),
-                Charging(n) => *o = Charging(*n + 1),
-                Flashing => *o = Charging(1),
-            }
-        }
-
-        while !should_flash.is_empty() {
-            let i = should_flash.pop().unwrap();
-
-            // Don't flash twice already!.
-            if let Flashing = self.octopuses[i] {
-                continue;
-            }
-
-            self.octopuses[i] = Flashing;
-
-            let adjacent = self.get_adjacent(i);
-            for adj in adjacent.into_iter() {
-                if let Some(o) = self.octopuses.get_mut(adj) {
-                    match o {
-                        Charging(9) => should_flash.push(adj),
-                        Charging(n) => *o = Charging(*n + 1),
-                        Flashing => continue,
-                    }
-                }
-            }
+    /// Steps a copy of the octopuses forever.
+    pub fn simulate(&self) -> Simulation {
+        Simulation {
+            octopuses: self.clone(),
+            step: 0,
         }
     }
 
-    fn get_adjacent(&self, i: usize) -> Vec<usize> {
-        let point_to_index = |p: (usize, usize)| p.1 * self.width + p.0;
-        let index_to_point = |i: usize| (i % self.width, i / self.height);
-
-        let mut result = Vec::with_capacity(8);
-
-        let i = index_to_point(i);
-
-        // Left
-        if i.0 > 0 {
-            result.push(point_to_index((i.0 - 1, i.1)))
-        }
-
-        // Right
-        if i.0 < self.width - 1 {
-            result.push(point_to_index((i.0 + 1, i.1)))
-        }
-
-        // Up
-        if i.1 > 0 {
-            result.push(point_to_index((i.0, i.1 - 1)))
-        }
-
-        // Down
-        if i.1 < self.height - 1 {
-            result.push(point_to_index((i.0, i.1 + 1)))
-        }
-
-        // TopLeft
-        if i.0 > 0 && i.1 > 0 {
-            result.push(point_to_index((i.0 - 1, i.1 - 1)))
-        }
-
-        // TopRight
-        if i.0 < self.width - 1 && i.1 > 0 {
-            result.push(point_to_index((i.0 + 1, i.1 - 1)))
-        }
+    /// Steps a copy of the octopuses until they repeat, at most `limit` times. The
+    /// flashes after any number of steps can then be read off without simulating them.
+    pub fn history(&self, limit: usize) -> History<DumboOctopuses> {
+        History::record(
+            self.clone(),
+            |octopuses| {
+                let mut octopuses = octopuses.clone();
+                octopuses.step();
+                octopuses
+            },
+            limit,
+        )
+    }
 
-        // BottomLeft
-        if i.0 > 0 && i.1 < self.height - 1 {
-            result.push(point_to_index((i.0 - 1, i.1 + 1)))
-        }
+    /// The first step where every octopus flashes, `None` when they start repeating
+    /// themselves without having done so.
+    pub fn first_all_flashed(&self) -> Option<usize> {
+        cycle::find_step(
+            self.clone(),
+            |octopuses| {
+                let mut octopuses = octopuses.clone();
+                octopuses.step();
+                octopuses
+            },
+            |octopuses| octopuses.all_flashed(),
+        )
+        .map(|n| n as usize)
+    }
 
-        // BottomRight
-        if i.0 < self.width - 1 && i.1 < self.height - 1 {
-            result.push(point_to_index((i.0 + 1, i.1 + 1)))
+    /// Every octopus gains a level, those past 9 flash and raise their neighbours in
+    /// turn, then settle at 0.
+    fn rule() -> Rule<Octopus> {
+        Rule {
+            neighbourhood: Neighbourhood::Moore,
+            update: Update::Cascading(Cascade {
+                charge: Box::new(|o| match o {
+                    Charging(n) => Charging(n + 1),
+                    Flashing => Charging(1),
+                }),
+                fires: Box::new(|o| matches!(o, Charging(n) if *n > 9)),
+                fire: Box::new(|_| Flashing),
+                propagate: Box::new(|o| match o {
+                    Charging(n) => Charging(n + 1),
+                    Flashing => Flashing,
+                }),
+            }),
         }
-
-        result
     }
 
-    fn count_flashed(&self) -> usize {
-        self.octopuses.iter().filter(|&o| o == &Flashing).count()
+    fn step(&mut self) {
+        self.octopuses.step(&Self::rule());
     }
-}
-
-impl<T: IntoIterator<Item = char>> From<T> for DumboOctopuses {
-    fn from(chain: T) -> Self {
-        let octopuses = chain.into_iter().map(Octopus::from).collect_vec();
 
-        let length = f32::sqrt(octopuses.len() as f32) as usize;
+    pub fn count_flashed(&self) -> usize {
+        self.octopuses
+            .cells
+            .cells()
+            .iter()
+            .filter(|&o| o == &Flashing)
+            .count()
+    }
 
-        DumboOctopuses {
-            octopuses,
-            width: length,
-            height: length,
-        }
+    pub fn all_flashed(&self) -> bool {
+        self.count_flashed() == self.octopuses.cells.len()
     }
 }
 
 impl Debug for DumboOctopuses {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let side = f32::sqrt(self.octopuses.len() as f32) as usize;
-
         let mut builder = String::new();
 
-        for i in 0..side {
-            for j in 0..side {
-                let part = match self.octopuses[i * side + j] {
+        for row in self.octopuses.cells.rows() {
+            for o in row {
+                let part = match o {
                     Flashing => format!("{}", Red.paint(format!("{}", 0))),
                     Charging(n) => format!("{:?}", n),
                 };
@@ -154,44 +128,106 @@ impl Debug for DumboOctopuses {
     }
 }
 
-fn parse_input_day11(input: &str) -> DumboOctopuses {
-    DumboOctopuses::from(input.lines().map(|c| c.chars().collect_vec()).flatten())
+impl Display for DumboOctopuses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.octopuses.cells.rows() {
+            for o in row {
+                match o {
+                    Flashing => write!(f, "0")?,
+                    Charging(n) => write!(f, "{}", n)?,
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The octopuses after a step of a [`Simulation`].
+pub struct Step {
+    /// Counting from 1.
+    pub number: usize,
+    pub flashes: usize,
+    pub octopuses: DumboOctopuses,
 }
 
-#[aoc(day11, part1)]
-fn day11_part1(input: &str) -> usize {
-    let mut dumbo = parse_input_day11(input);
+pub struct Simulation {
+    octopuses: DumboOctopuses,
+    step: usize,
+}
 
-    let mut sum = 0;
+impl Iterator for Simulation {
+    type Item = Step;
 
-    for _ in 0..100 {
-        dumbo.step();
-        sum += dumbo.count_flashed();
+    fn next(&mut self) -> Option<Self::Item> {
+        self.octopuses.step();
+        self.step += 1;
 
-        println!("{:?}", dumbo);
+        Some(Step {
+            number: self.step,
+            flashes: self.octopuses.count_flashed(),
+            octopuses: self.octopuses.clone(),
+        })
     }
+}
 
-    sum
+/// Draws steps one after the other on an ANSI terminal, flashing octopuses in red.
+pub struct Animation {
+    frame: Duration,
 }
 
-#[aoc(day11, part2)]
-fn day11_part2(input: &str) -> Option<usize> {
-    let mut dumbo = parse_input_day11(input);
+impl Animation {
+    pub fn new(frames_per_second: f64) -> Self {
+        assert!(
+            frames_per_second > 0.0,
+            "an animation needs a positive frame rate"
+        );
 
-    for i in 1.. {
-        dumbo.step();
+        Animation {
+            frame: Duration::from_secs_f64(1.0 / frames_per_second),
+        }
+    }
+
+    pub fn play(&self, steps: impl Iterator<Item = Step>, out: &mut impl Write) -> io::Result<()> {
+        for step in steps {
+            // Clears the screen and moves the cursor back to the top left corner.
+            write!(out, "\x1b[2J\x1b[H")?;
+            write!(out, "{:?}", step.octopuses)?;
+            writeln!(out, "step {}: {} flashes", step.number, step.flashes)?;
+            out.flush()?;
 
-        if dumbo.count_flashed() == 100 {
-            return Some(i);
+            thread::sleep(self.frame);
         }
+
+        Ok(())
     }
+}
+
+#[aoc_generator(day11)]
+fn parse_input_day11(input: &str) -> Result<DumboOctopuses, ParseError> {
+    let source = Source::new(11, input);
 
-    None
+    Ok(DumboOctopuses {
+        octopuses: Automaton::new(Grid::parse_with(&source, input, Octopus::from_char)?),
+    })
+}
+
+#[aoc(day11, part1)]
+fn day11_part1(input: &DumboOctopuses) -> usize {
+    input.simulate().take(100).map(|s| s.flashes).sum()
+}
+
+#[aoc(day11, part2)]
+fn day11_part2(input: &DumboOctopuses) -> Option<usize> {
+    input.first_all_flashed()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     #[test]
     fn test_part1() {
@@ -206,7 +242,7 @@ mod tests {
 4846848554
 5283751526";
 
-        assert_eq!(day11_part1(&input), 1656);
+        assert_eq!(day11_part1(&parse_input_day11(input).unwrap()), 1656);
     }
 
     #[test]
@@ -222,6 +258,108 @@ mod tests {
 4846848554
 5283751526";
 
-        assert_eq!(day11_part2(&input), Some(195));
+        assert_eq!(day11_part2(&parse_input_day11(input).unwrap()), Some(195));
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let input = parse_input_day11("5483143223\n2745854711\n5264556173").unwrap();
+        assert_eq!(day11_part1(&input), 429);
+
+        let input = parse_input_day11("99999\n99999").unwrap();
+        assert_eq!(day11_part2(&input), Some(1));
+
+        assert!(parse_input_day11("999\n99").is_err());
+    }
+
+    #[test]
+    fn test_simulation() {
+        let input = parse_input_day11(
+            "5483143223
+2745854711
+5264556173
+6141336146
+6357385478
+4167524645
+2176841721
+6882881134
+4846848554
+5283751526",
+        )
+        .unwrap();
+
+        let steps = input.simulate().take(2).collect_vec();
+
+        assert_eq!(steps[0].flashes, 0);
+        assert_eq!(
+            steps[0].octopuses.to_string().lines().next(),
+            Some("6594254334")
+        );
+        assert_eq!(steps[1].number, 2);
+        assert_eq!(steps[1].flashes, 35);
+        assert_eq!(
+            steps[1].octopuses.to_string().lines().next(),
+            Some("8807476555")
+        );
+    }
+
+    #[test]
+    fn test_animation() {
+        let input = parse_input_day11("99\n99").unwrap();
+        let mut out = Vec::new();
+
+        Animation::new(f64::INFINITY)
+            .play(input.simulate().take(2), &mut out)
+            .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("\x1b[2J").count(), 2);
+        assert!(out.contains("step 1: 4 flashes"));
+        assert!(out.contains("step 2: 0 flashes"));
+    }
+
+    #[test]
+    #[should_panic(expected = "positive frame rate")]
+    fn test_animation_frame_rate() {
+        Animation::new(0.0);
+    }
+
+    #[test]
+    fn test_history() {
+        let input = parse_input_day11(
+            "5483143223
+2745854711
+5264556173
+6141336146
+6357385478
+4167524645
+2176841721
+6882881134
+4846848554
+5283751526",
+        )
+        .unwrap();
+        let history = input.history(10_000);
+        let flashes = |o: &DumboOctopuses| o.count_flashed() as u64;
+
+        for steps in [10, 100, 195, 1000] {
+            let simulated: usize = input.simulate().take(steps).map(|s| s.flashes).sum();
+            assert_eq!(history.sum(steps as u64, flashes), Some(simulated as u64));
+        }
+
+        // From step 195 on, all of them flash together every 10 steps.
+        let synchronised = history.sum(195, flashes).unwrap();
+        assert_eq!(
+            history.sum(1_000_000_000, flashes),
+            Some(synchronised + 100 * ((1_000_000_000 - 195) / 10))
+        );
+        assert!(history.state(1_000_000_005).unwrap().all_flashed());
+
+        // All of them flash again when coming back to the starting state.
+        assert_eq!(
+            parse_input_day11("0").unwrap().first_all_flashed(),
+            Some(10)
+        );
+        assert_eq!(input.first_all_flashed(), Some(195));
     }
 }
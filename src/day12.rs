@@ -1,59 +1,236 @@
 use crate::day12::Cave::{End, Small, Start};
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::hash::Hash;
 
 #[derive(Eq, PartialEq, Hash, Debug, Clone)]
-enum Cave {
+pub enum Cave {
     Start,
     End,
     Big(String),
     Small(String),
 }
 
-type Path = Vec<Cave>;
+pub type Path = Vec<Cave>;
+
+/// The most small caves a [`VisitPolicy`] can tell apart.
+pub const MAX_SMALL_CAVES: usize = 64;
+
+/// Decides which caves a path may step into, one step at a time. Small caves are known
+/// by an index below [`MAX_SMALL_CAVES`], the same one for the whole search.
+pub trait VisitPolicy {
+    /// What the policy remembers about the path so far.
+    type State: Clone + Eq + Hash;
+
+    /// The state of a path that hasn't left its first cave yet.
+    fn start(&self) -> Self::State;
+
+    /// The state after stepping into a cave, `small` being its index if it is a small
+    /// one. `None` when the step isn't allowed.
+    fn visit(&self, state: &Self::State, small: Option<usize>) -> Option<Self::State>;
+}
+
+/// Remembers the visited small caves as a bitmask.
+pub struct EachSmallCaveOnce;
+
+impl VisitPolicy for EachSmallCaveOnce {
+    type State = u64;
+
+    fn start(&self) -> Self::State {
+        0
+    }
+
+    fn visit(&self, &visited: &Self::State, small: Option<usize>) -> Option<Self::State> {
+        match small {
+            Some(i) if visited & 1 << i != 0 => None,
+            Some(i) => Some(visited | 1 << i),
+            None => Some(visited),
+        }
+    }
+}
+
+/// Every small cave once, except for a single one which may be visited twice.
+pub struct OneSmallCaveTwice;
+
+impl VisitPolicy for OneSmallCaveTwice {
+    /// The visited small caves and whether the second visit was used up.
+    type State = (u64, bool);
+
+    fn start(&self) -> Self::State {
+        (0, false)
+    }
+
+    fn visit(
+        &self,
+        &(visited, revisited): &Self::State,
+        small: Option<usize>,
+    ) -> Option<Self::State> {
+        match small {
+            Some(i) if visited & 1 << i == 0 => Some((visited | 1 << i, revisited)),
+            Some(_) if !revisited => Some((visited, true)),
+            Some(_) => None,
+            None => Some((visited, revisited)),
+        }
+    }
+}
+
+/// Any small cave up to the given number of times, at most 255.
+pub struct SmallCavesUpTo(pub u8);
+
+impl VisitPolicy for SmallCavesUpTo {
+    /// The visits of each small cave.
+    type State = [u8; MAX_SMALL_CAVES];
+
+    fn start(&self) -> Self::State {
+        [0; MAX_SMALL_CAVES]
+    }
+
+    fn visit(&self, visits: &Self::State, small: Option<usize>) -> Option<Self::State> {
+        let mut visits = *visits;
+
+        if let Some(i) = small {
+            if visits[i] == self.0 {
+                return None;
+            }
+            visits[i] += 1;
+        }
+
+        Some(visits)
+    }
+}
+
+/// Follows `policy` for at most `steps` steps, which also keeps paths through
+/// neighbouring big caves finite.
+pub struct Budgeted<P> {
+    pub policy: P,
+    pub steps: usize,
+}
+
+impl<P: VisitPolicy> VisitPolicy for Budgeted<P> {
+    /// The inner state and the steps taken.
+    type State = (P::State, usize);
+
+    fn start(&self) -> Self::State {
+        (self.policy.start(), 0)
+    }
+
+    fn visit(&self, (state, steps): &Self::State, small: Option<usize>) -> Option<Self::State> {
+        if *steps == self.steps {
+            return None;
+        }
+
+        Some((self.policy.visit(state, small)?, steps + 1))
+    }
+}
 
 #[derive(Default)]
-struct AdjacencyList {
+pub struct AdjacencyList {
     map: HashMap<Cave, Vec<Cave>>,
 }
 
+/// Memoizes path counts per cave and policy state.
+struct PathCounter<'a, P: VisitPolicy> {
+    list: &'a AdjacencyList,
+    from: &'a Cave,
+    to: &'a Cave,
+    policy: &'a P,
+    small_caves: HashMap<&'a Cave, usize>,
+    memo: HashMap<(&'a Cave, P::State), usize>,
+}
+
+impl<'a, P: VisitPolicy> PathCounter<'a, P> {
+    fn count(&mut self, cave: &'a Cave, state: P::State) -> usize {
+        if cave == self.to {
+            return 1;
+        }
+
+        if let Some(&n) = self.memo.get(&(cave, state.clone())) {
+            return n;
+        }
+
+        let list = self.list;
+        let mut n = 0;
+
+        for next in list.map.get(cave).into_iter().flatten() {
+            if next == self.from {
+                continue;
+            }
+
+            let small = self.small_caves.get(next).cloned();
+            if let Some(next_state) = self.policy.visit(&state, small) {
+                n += self.count(next, next_state);
+            }
+        }
+
+        self.memo.insert((cave, state), n);
+
+        n
+    }
+}
+
 impl AdjacencyList {
     fn new() -> Self {
         Default::default()
     }
 
-    fn find_paths(&self, from: Cave, to: Cave, is_good_path: fn(&Path) -> bool) -> Vec<Path> {
-        let mut paths = Vec::new();
+    /// Numbers the small caves for the [`VisitPolicy`].
+    fn small_caves(&self) -> HashMap<&Cave, usize> {
+        let small_caves: HashMap<&Cave, usize> = self
+            .map
+            .keys()
+            .filter(|c| matches!(c, Small(_)))
+            .enumerate()
+            .map(|(i, c)| (c, i))
+            .collect();
 
-        let mut stack = vec![vec![from.clone()]];
+        assert!(
+            small_caves.len() <= MAX_SMALL_CAVES,
+            "too many small caves for a visit policy"
+        );
 
-        while !stack.is_empty() {
-            let current = stack.pop().unwrap();
+        small_caves
+    }
 
-            let last_step = current.last().unwrap().clone();
+    /// Counts the paths from `from` to `to` that `policy` allows, without building them.
+    pub fn count_paths(&self, from: &Cave, to: &Cave, policy: &impl VisitPolicy) -> usize {
+        PathCounter {
+            list: self,
+            from,
+            to,
+            policy,
+            small_caves: self.small_caves(),
+            memo: HashMap::new(),
+        }
+        .count(from, policy.start())
+    }
 
-            if last_step == to {
-                paths.push(current);
+    pub fn find_paths<P: VisitPolicy>(&self, from: Cave, to: Cave, policy: &P) -> Vec<Path> {
+        let small_caves = self.small_caves();
+        let mut paths = Vec::new();
 
-                continue;
-            }
+        let mut stack = vec![(vec![from.clone()], policy.start())];
 
-            if !is_good_path(&current) {
-                continue;
-            }
+        while let Some((current, state)) = stack.pop() {
+            let last_step = current.last().unwrap();
+
+            if *last_step == to {
+                paths.push(current);
 
-            if last_step == from && current.len() > 1 {
                 continue;
             }
 
-            // stack.extend(self.map.get(&last_step).unwrap_or(&Vec::new()).clone());
-            if let Some(connections) = self.map.get(&last_step) {
-                for connection in connections.clone() {
+            for connection in self.map.get(last_step).into_iter().flatten() {
+                if *connection == from {
+                    continue;
+                }
+
+                let small = small_caves.get(connection).cloned();
+                if let Some(next_state) = policy.visit(&state, small) {
                     let mut clone = current.clone();
-                    clone.push(connection);
+                    clone.push(connection.clone());
 
-                    stack.push(clone);
+                    stack.push((clone, next_state));
                 }
             }
         }
@@ -62,14 +239,15 @@ impl AdjacencyList {
     }
 }
 
-impl From<&str> for Cave {
-    fn from(s: &str) -> Self {
+impl Cave {
+    fn parse(source: &Source, s: &str) -> Result<Self, ParseError> {
         match s {
-            "start" => Cave::Start,
-            "end" => Cave::End,
-            s if s.to_lowercase() == s => Cave::Small(s.to_string()),
-            s if s.to_uppercase() == s => Cave::Big(s.to_string()),
-            _ => unreachable!(),
+            "start" => Ok(Cave::Start),
+            "end" => Ok(Cave::End),
+            "" => Err(source.missing(s, "a cave name")),
+            s if s.to_lowercase() == s => Ok(Cave::Small(s.to_string())),
+            s if s.to_uppercase() == s => Ok(Cave::Big(s.to_string())),
+            _ => Err(source.unexpected(s)),
         }
     }
 }
@@ -88,52 +266,33 @@ impl FromIterator<(Cave, Cave)> for AdjacencyList {
 }
 
 #[aoc_generator(day12)]
-fn parse_input_day12(input: &str) -> AdjacencyList {
+fn parse_input_day12(input: &str) -> Result<AdjacencyList, ParseError> {
+    let source = Source::new(12, input);
+
     input
         .lines()
         .map(|l| {
-            l.split('-')
-                .map(Cave::from)
-                .collect_tuple::<(Cave, Cave)>()
-                .unwrap()
+            let (from, to) = source.split_once(l, "-")?;
+
+            Ok((Cave::parse(&source, from)?, Cave::parse(&source, to)?))
         })
         .collect()
 }
 
 #[aoc(day12, part1)]
 fn day12_part1(input: &AdjacencyList) -> Option<usize> {
-    let is_good_path = |p: &Path| -> bool {
-        let last_step = p.last().unwrap().clone();
-
-        if let Small(_) = last_step {
-            p.iter().filter(|&c| *c == last_step).count() == 1
-        } else {
-            true
-        }
-    };
-
-    Some(input.find_paths(Start, End, is_good_path).len())
+    Some(input.count_paths(&Start, &End, &EachSmallCaveOnce))
 }
 
 #[aoc(day12, part2)]
 fn day12_part2(input: &AdjacencyList) -> Option<usize> {
-    let is_good_path = |p: &Path| -> bool {
-        let all_smalls = p
-            .iter()
-            .filter_map(|c| if let Small(s) = c { Some(s) } else { None })
-            .collect_vec();
-
-        let set = all_smalls.iter().collect::<HashSet<_>>();
-
-        set.len() == all_smalls.len() || set.len() + 1 == all_smalls.len()
-    };
-
-    Some(input.find_paths(Start, End, is_good_path).len())
+    Some(input.count_paths(&Start, &End, &OneSmallCaveTwice))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     #[test]
     fn test_part1() {
@@ -144,7 +303,7 @@ A-b
 b-d
 A-end
 b-end";
-        assert_eq!(day12_part1(&parse_input_day12(input)), Some(10));
+        assert_eq!(day12_part1(&parse_input_day12(input).unwrap()), Some(10));
     }
 
     #[test]
@@ -156,6 +315,89 @@ A-b
 b-d
 A-end
 b-end";
-        assert_eq!(day12_part2(&parse_input_day12(input)), Some(36));
+        assert_eq!(day12_part2(&parse_input_day12(input).unwrap()), Some(36));
+    }
+
+    #[test]
+    fn test_count_matches_find() {
+        let input = parse_input_day12(
+            "fs-end
+he-DX
+fs-he
+start-DX
+pj-DX
+end-zg
+zg-sl
+zg-pj
+pj-he
+RW-he
+fs-DX
+pj-RW
+zg-RW
+start-pj
+he-WI
+zg-he
+pj-fs
+start-RW",
+        )
+        .unwrap();
+
+        assert_eq!(input.count_paths(&Start, &End, &EachSmallCaveOnce), 226);
+        assert_eq!(input.find_paths(Start, End, &EachSmallCaveOnce).len(), 226);
+        assert_eq!(input.count_paths(&Start, &End, &OneSmallCaveTwice), 3509);
+        assert_eq!(input.find_paths(Start, End, &OneSmallCaveTwice).len(), 3509);
+    }
+
+    #[test]
+    fn test_policies() {
+        let input = parse_input_day12(
+            "start-A
+start-b
+A-c
+A-b
+b-d
+A-end
+b-end",
+        )
+        .unwrap();
+
+        assert_eq!(input.count_paths(&Start, &End, &SmallCavesUpTo(1)), 10);
+
+        // Allowing every small cave twice includes the paths revisiting only one.
+        let paths = input.find_paths(Start, End, &SmallCavesUpTo(2));
+        assert_eq!(
+            input.count_paths(&Start, &End, &SmallCavesUpTo(2)),
+            paths.len()
+        );
+        let one_twice = paths
+            .iter()
+            .filter(|p| {
+                let smalls = p.iter().filter(|c| matches!(c, Small(_))).collect_vec();
+                smalls.len() <= smalls.iter().unique().count() + 1
+            })
+            .count();
+        assert_eq!(one_twice, 36);
+        assert!(paths.len() > 36);
+
+        let budgeted = Budgeted {
+            policy: OneSmallCaveTwice,
+            steps: 4,
+        };
+        let short = input
+            .find_paths(Start, End, &OneSmallCaveTwice)
+            .into_iter()
+            .filter(|p| p.len() <= 5)
+            .collect_vec();
+        assert_eq!(input.find_paths(Start, End, &budgeted).len(), short.len());
+        assert_eq!(input.count_paths(&Start, &End, &budgeted), short.len());
+
+        // Neighbouring big caves loop forever without a budget, with it going back and
+        // forth between A and B fits once.
+        let input = parse_input_day12("start-A\nA-B\nB-end").unwrap();
+        let budgeted = Budgeted {
+            policy: EachSmallCaveOnce,
+            steps: 6,
+        };
+        assert_eq!(input.count_paths(&Start, &End, &budgeted), 2);
     }
 }
@@ -1,6 +1,8 @@
+use crate::grid::{Grid, Point as Cell};
+use crate::ocr;
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use regex::Regex;
 use std::collections::HashSet;
 
 #[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -37,25 +39,29 @@ struct Fold {
     value: usize,
 }
 
-impl From<(&str, &str)> for Point {
-    fn from(t: (&str, &str)) -> Self {
-        Point {
-            x: t.0.parse().unwrap(),
-            y: t.1.parse().unwrap(),
-        }
+impl Point {
+    fn parse(source: &Source, l: &str) -> Result<Self, ParseError> {
+        let (x, y) = source.split_once(l, ",")?;
+
+        Ok(Point {
+            x: source.number(x)?,
+            y: source.number(y)?,
+        })
     }
 }
 
-impl From<(&str, &str)> for Fold {
-    fn from(t: (&str, &str)) -> Self {
-        Fold {
-            axis: match t.0 {
+impl Fold {
+    fn parse(source: &Source, l: &str) -> Result<Self, ParseError> {
+        let (axis, value) = source.split_once(l.trim_start_matches("fold along "), "=")?;
+
+        Ok(Fold {
+            axis: match axis {
                 "x" => Axis::XAxis,
                 "y" => Axis::YAxis,
-                _ => unreachable!(),
+                _ => return Err(source.unexpected(axis)),
             },
-            value: t.1.parse().unwrap(),
-        }
+            value: source.number(value)?,
+        })
     }
 }
 
@@ -66,32 +72,21 @@ struct ParsedInput {
 }
 
 #[aoc_generator(day13)]
-fn parse_input_day13(input: &str) -> ParsedInput {
-    let fold_regex = Regex::new(r"(?P<axis>[xy])=(?P<value>\d+)").unwrap();
-    let mut parts = input.split("\n\n");
-
-    ParsedInput {
-        points: parts
-            .next()
-            .unwrap()
+fn parse_input_day13(input: &str) -> Result<ParsedInput, ParseError> {
+    let source = Source::new(13, input);
+
+    let (points, folds) = source.split_once(input, "\n\n")?;
+
+    Ok(ParsedInput {
+        points: points
             .lines()
-            .map(|l| {
-                l.split(',')
-                    .collect_tuple::<(&str, &str)>()
-                    .map(Point::from)
-                    .unwrap()
-            })
-            .collect(),
-        folds: fold_regex
-            .captures_iter(parts.next().unwrap())
-            .map(|capture| {
-                Fold::from((
-                    capture.name("axis").unwrap().as_str(),
-                    capture.name("value").unwrap().as_str(),
-                ))
-            })
-            .collect(),
-    }
+            .map(|l| Point::parse(&source, l))
+            .collect::<Result<_, _>>()?,
+        folds: folds
+            .lines()
+            .map(|l| Fold::parse(&source, l))
+            .collect::<Result<_, _>>()?,
+    })
 }
 
 #[aoc(day13, part1)]
@@ -100,19 +95,19 @@ fn day13_part1(input: &ParsedInput) -> Option<usize> {
 }
 
 #[aoc(day13, part2)]
-fn day13_part2(input: &ParsedInput) -> Option<usize> {
+fn day13_part2(input: &ParsedInput) -> Option<String> {
     let points = fold(&input.points, &input.folds);
 
-    let max_x = points.iter().map(|p| p.x).max().unwrap();
-    let max_y = points.iter().map(|p| p.y).max().unwrap();
+    let max_x = points.iter().map(|p| p.x).max().unwrap_or_default();
+    let max_y = points.iter().map(|p| p.y).max().unwrap_or_default();
 
-    let mut grid = vec![vec![' '; max_x + 1]; max_y + 1];
+    let mut pixels = Grid::filled(max_x + 1, max_y + 1, false);
 
-    points.iter().for_each(|p| grid[p.y][p.x] = '#');
-
-    println!("{}", grid.iter().map(|r| r.iter().join("")).join("\n"));
+    points
+        .iter()
+        .for_each(|p| pixels[Cell::new(p.x, p.y)] = true);
 
-    Some(1)
+    ocr::recognize(&pixels).filter(|letters| !letters.contains('?'))
 }
 
 fn fold(points: &[Point], folds: &[Fold]) -> Vec<Point> {
@@ -177,11 +172,65 @@ mod tests {
 
 fold along y=7
 fold along x=5";
-        assert_eq!(day13_part1(&parse_input_day13(input)), Some(17));
+        assert_eq!(day13_part1(&parse_input_day13(input).unwrap()), Some(17));
     }
 
     #[test]
     fn test_part2() {
+        let input = "1,12
+2,0
+0,11
+3,1
+0,2
+3,10
+0,9
+1,3
+2,9
+3,3
+0,4
+3,8
+0,7
+3,5
+5,12
+6,0
+7,12
+5,1
+8,11
+5,10
+8,2
+5,3
+6,9
+7,3
+5,8
+7,8
+5,5
+8,7
+10,0
+11,12
+12,0
+13,12
+10,11
+10,2
+11,10
+12,2
+10,9
+10,4
+10,7
+11,5
+12,7
+13,5
+
+fold along y=6";
+
+        assert_eq!(
+            day13_part2(&parse_input_day13(input).unwrap()),
+            Some("ARE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unreadable() {
+        // The example folds into a 5 pixel high square, too short for either font.
         let input = "6,10
 0,14
 9,10
@@ -203,16 +252,14 @@ fold along x=5";
 
 fold along y=7
 fold along x=5";
+        assert_eq!(day13_part2(&parse_input_day13(input).unwrap()), None);
 
-        /*
-        ###..####.#..#.###...##....##.####.#..#
-        #..#.#....#.#..#..#.#..#....#....#.#..#
-        ###..###..##...#..#.#.......#...#..#..#
-        #..#.#....#.#..###..#.......#..#...#..#
-        #..#.#....#.#..#.#..#..#.#..#.#....#..#
-        ###..#....#..#.#..#..##...##..####..##.
-        */
-
-        assert_eq!(day13_part2(&parse_input_day13(input)), Some(1));
+        // A filled 4x6 block is as tall as a letter without being one.
+        let block = (0..4)
+            .flat_map(|x| (0..6).map(move |y| format!("{},{}", x, y)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let input = format!("{}\n9,0\n\nfold along x=8", block);
+        assert_eq!(day13_part2(&parse_input_day13(&input).unwrap()), None);
     }
 }
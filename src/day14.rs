@@ -1,3 +1,4 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -15,24 +16,34 @@ struct Input {
 }
 
 #[aoc_generator(day14)]
-fn parse_input_day14(input: &str) -> Input {
-    let parts = input.split_once("\n\n").unwrap();
+fn parse_input_day14(input: &str) -> Result<Input, ParseError> {
+    let source = Source::new(14, input);
 
-    Input {
+    let parts = source.split_once(input, "\n\n")?;
+
+    Ok(Input {
         polymer_template: parts.0.chars().collect(),
         pair_insertion_rules: parts
             .1
             .lines()
             .map(|l| {
-                let parts = l.split_once(" -> ").unwrap();
-
-                InsertionRule {
-                    sequence: parts.0.chars().collect_tuple().unwrap(),
-                    insert: parts.1.chars().next().unwrap(),
-                }
+                let parts = source.split_once(l, " -> ")?;
+
+                Ok(InsertionRule {
+                    sequence: parts
+                        .0
+                        .chars()
+                        .collect_tuple()
+                        .ok_or_else(|| source.unexpected(parts.0))?,
+                    insert: parts
+                        .1
+                        .chars()
+                        .exactly_one()
+                        .map_err(|_| source.unexpected(parts.1))?,
+                })
             })
-            .collect(),
-    }
+            .collect::<Result<_, _>>()?,
+    })
 }
 
 #[aoc(day14, part1)]
@@ -116,7 +127,7 @@ BB -> N
 BC -> B
 CC -> N
 CN -> C";
-        assert_eq!(day14_part1(&parse_input_day14(input)), Some(1588));
+        assert_eq!(day14_part1(&parse_input_day14(input).unwrap()), Some(1588));
     }
 
     #[test]
@@ -139,6 +150,9 @@ BB -> N
 BC -> B
 CC -> N
 CN -> C";
-        assert_eq!(day14_part2(&parse_input_day14(input)), Some(2188189693529));
+        assert_eq!(
+            day14_part2(&parse_input_day14(input).unwrap()),
+            Some(2188189693529)
+        );
     }
 }
@@ -1,11 +1,12 @@
 use crate::day16::Bit::{One, Zero};
 use crate::day16::PacketType::Operator;
-use aoc_runner_derive::aoc;
+use crate::parse::{ParseError, Source};
+use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use std::str::Chars;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
-enum Bit {
+pub enum Bit {
     Zero,
     One,
 }
@@ -49,13 +50,13 @@ impl Iterator for BitsIterator {
     }
 }
 
-struct Scanner<'a> {
+pub struct Scanner<'a> {
     chars: Chars<'a>,
     nibble_iterator: BitsIterator,
 }
 
 impl<'a> Scanner<'a> {
-    fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str) -> Self {
         let mut chars = input.chars();
         let current_nibble = chars.next().unwrap().to_digit(16).unwrap() as usize;
         Scanner {
@@ -79,10 +80,10 @@ impl<'a> Iterator for Scanner<'a> {
     }
 }
 
-type PacketVersion = u8;
+pub type PacketVersion = u8;
 
-#[derive(Debug)]
-enum Op {
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Op {
     Sum,
     Product,
     Minimum,
@@ -107,33 +108,150 @@ impl From<u8> for Op {
     }
 }
 
-#[derive(Debug)]
-enum PacketType {
+impl From<Op> for u8 {
+    fn from(op: Op) -> Self {
+        match op {
+            Op::Sum => 0,
+            Op::Product => 1,
+            Op::Minimum => 2,
+            Op::Maximum => 3,
+            Op::GreaterThan => 5,
+            Op::LessThan => 6,
+            Op::EqualTo => 7,
+        }
+    }
+}
+
+/// How an operator packet announces its sub-packets.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum LengthType {
+    /// Type 0, the total length of the sub-packets in bits.
+    Bits,
+    /// Type 1, the number of sub-packets.
+    Count,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum PacketType {
     Literal(usize),
-    Operator { op: Op, packets: Vec<Packet> },
+    Operator {
+        op: Op,
+        length_type: LengthType,
+        packets: Vec<Packet>,
+    },
 }
 
-#[derive(Debug)]
-struct Packet {
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Packet {
+    pub version: PacketVersion,
+    pub packet_type: PacketType,
+    /// The number of bits the packet takes in a transmission.
+    pub size: usize,
+}
+
+const LITERAL_TYPE_ID: u8 = 4;
+const BITS_LENGTH_SIZE: usize = 15;
+const COUNT_LENGTH_SIZE: usize = 11;
+
+pub struct PacketBuilder {
     version: PacketVersion,
     packet_type: PacketType,
-    size: usize,
+    length_type: Option<LengthType>,
 }
 
-struct Parser<'a> {
+impl PacketBuilder {
+    pub fn literal(value: usize) -> Self {
+        PacketBuilder {
+            version: 0,
+            packet_type: PacketType::Literal(value),
+            length_type: None,
+        }
+    }
+
+    pub fn operator(op: Op) -> Self {
+        PacketBuilder {
+            version: 0,
+            packet_type: PacketType::Operator {
+                op,
+                length_type: LengthType::Count,
+                packets: Vec::new(),
+            },
+            length_type: None,
+        }
+    }
+
+    pub fn version(mut self, version: PacketVersion) -> Self {
+        assert!(version < 8, "versions only have 3 bits");
+
+        self.version = version;
+        self
+    }
+
+    /// Forces the length type, by default the shorter count is used whenever the
+    /// number of sub-packets fits in it.
+    pub fn length_type(mut self, length_type: LengthType) -> Self {
+        self.length_type = Some(length_type);
+        self
+    }
+
+    pub fn packet(mut self, packet: Packet) -> Self {
+        if let PacketType::Operator {
+            ref mut packets, ..
+        } = self.packet_type
+        {
+            packets.push(packet);
+        } else {
+            panic!("literal packets have no sub-packets");
+        }
+
+        self
+    }
+
+    pub fn packets(self, packets: impl IntoIterator<Item = Packet>) -> Self {
+        packets.into_iter().fold(self, PacketBuilder::packet)
+    }
+
+    pub fn build(self) -> Packet {
+        let packet_type = match self.packet_type {
+            PacketType::Operator { op, packets, .. } => PacketType::Operator {
+                op,
+                length_type: self.length_type.unwrap_or(
+                    if packets.len() < 1 << COUNT_LENGTH_SIZE {
+                        LengthType::Count
+                    } else {
+                        LengthType::Bits
+                    },
+                ),
+                packets,
+            },
+            literal => literal,
+        };
+
+        let mut packet = Packet {
+            version: self.version,
+            packet_type,
+            size: 0,
+        };
+        packet.size = Encoder {}.bits(&packet).len();
+
+        packet
+    }
+}
+
+pub struct Parser<'a> {
     scanner: Scanner<'a>,
     consumed_bits: usize,
 }
 
 impl<'a> Parser<'a> {
-    fn new(scanner: Scanner<'a>) -> Self {
+    pub fn new(scanner: Scanner<'a>) -> Self {
         Parser {
             scanner,
             consumed_bits: 0,
         }
     }
 
-    fn parse(&mut self) -> Packet {
+    pub fn parse(&mut self) -> Packet {
         self.parse_packet()
     }
 
@@ -165,7 +283,7 @@ impl<'a> Parser<'a> {
 
         let v = u8::from_str_radix(&self.concat(bits), 2).unwrap();
 
-        if v == 4 {
+        if v == LITERAL_TYPE_ID {
             self.parse_literal_packet()
         } else {
             self.parse_operator_packet(v)
@@ -178,7 +296,7 @@ impl<'a> Parser<'a> {
         loop {
             let bits = self.eat(5);
 
-            builder.extend(bits.iter().cloned().skip(1));
+            builder.extend(bits.iter().skip(1).cloned());
 
             if bits[0] == Zero {
                 break;
@@ -193,9 +311,9 @@ impl<'a> Parser<'a> {
     fn parse_operator_packet(&mut self, value: u8) -> PacketType {
         let length_type_id = self.eat(1).first().cloned().unwrap();
 
-        let packets = match length_type_id {
+        let (length_type, packets) = match length_type_id {
             Zero => {
-                let total_length_in_bits = self.eat(15);
+                let total_length_in_bits = self.eat(BITS_LENGTH_SIZE);
                 let total_length_in_bits =
                     usize::from_str_radix(&self.concat(total_length_in_bits), 2).unwrap();
 
@@ -208,10 +326,10 @@ impl<'a> Parser<'a> {
                     packets.push(p);
                 }
 
-                packets
+                (LengthType::Bits, packets)
             }
             One => {
-                let number_of_inner_sub_packets = self.eat(11);
+                let number_of_inner_sub_packets = self.eat(COUNT_LENGTH_SIZE);
                 let number_of_inner_sub_packets =
                     usize::from_str_radix(&self.concat(number_of_inner_sub_packets), 2).unwrap();
 
@@ -220,12 +338,13 @@ impl<'a> Parser<'a> {
                     packets.push(self.parse_packet())
                 }
 
-                packets
+                (LengthType::Count, packets)
             }
         };
 
         PacketType::Operator {
             op: value.into(),
+            length_type,
             packets,
         }
     }
@@ -258,10 +377,10 @@ impl<'a> Parser<'a> {
     }
 }
 
-struct Interpreter {}
+pub struct Interpreter {}
 
 impl Interpreter {
-    fn evaluate(&self, p: &Packet) -> usize {
+    pub fn evaluate(&self, p: &Packet) -> usize {
         match p.packet_type {
             PacketType::Literal(v) => v,
             PacketType::Operator { .. } => self.evaluate_type_operator(&p.packet_type),
@@ -269,7 +388,7 @@ impl Interpreter {
     }
 
     fn evaluate_type_operator(&self, packet_type: &PacketType) -> usize {
-        if let PacketType::Operator { op, packets } = packet_type {
+        if let PacketType::Operator { op, packets, .. } = packet_type {
             match op {
                 Op::Sum => self.evaluate_op_sum(packets),
                 Op::Product => self.evaluate_op_product(packets),
@@ -318,6 +437,102 @@ impl Interpreter {
     }
 }
 
+pub struct Encoder {}
+
+impl Encoder {
+    /// Serializes `p` as a hex transmission, zero padded to a whole number of bytes.
+    pub fn encode(&self, p: &Packet) -> String {
+        let mut bits = self.bits(p);
+        bits.resize(bits.len().div_ceil(8) * 8, Zero);
+
+        bits.chunks(4)
+            .map(|nibble| {
+                let v = nibble.iter().fold(0, |v, b| v << 1 | (*b == One) as u32);
+                std::char::from_digit(v, 16).unwrap().to_ascii_uppercase()
+            })
+            .collect()
+    }
+
+    fn bits(&self, p: &Packet) -> Vec<Bit> {
+        let mut bits = Vec::new();
+        self.write_packet(p, &mut bits);
+        bits
+    }
+
+    fn write_packet(&self, p: &Packet, bits: &mut Vec<Bit>) {
+        self.write_number(p.version as usize, 3, bits);
+
+        match &p.packet_type {
+            PacketType::Literal(v) => {
+                self.write_number(LITERAL_TYPE_ID as usize, 3, bits);
+                self.write_literal(*v, bits);
+            }
+            PacketType::Operator {
+                op,
+                length_type,
+                packets,
+            } => {
+                self.write_number(u8::from(*op) as usize, 3, bits);
+                self.write_operator(*length_type, packets, bits);
+            }
+        }
+    }
+
+    fn write_literal(&self, v: usize, bits: &mut Vec<Bit>) {
+        let groups = (usize::BITS - v.leading_zeros()).div_ceil(4).max(1);
+
+        for group in (0..groups).rev() {
+            bits.push(if group == 0 { Zero } else { One });
+            self.write_number((v >> (group * 4)) & 0xF, 4, bits);
+        }
+    }
+
+    fn write_operator(&self, length_type: LengthType, packets: &[Packet], bits: &mut Vec<Bit>) {
+        let mut body = Vec::new();
+        for p in packets {
+            self.write_packet(p, &mut body);
+        }
+
+        match length_type {
+            LengthType::Bits => {
+                assert!(body.len() < 1 << BITS_LENGTH_SIZE, "sub-packets too long");
+
+                bits.push(Zero);
+                self.write_number(body.len(), BITS_LENGTH_SIZE, bits);
+            }
+            LengthType::Count => {
+                assert!(
+                    packets.len() < 1 << COUNT_LENGTH_SIZE,
+                    "too many sub-packets"
+                );
+
+                bits.push(One);
+                self.write_number(packets.len(), COUNT_LENGTH_SIZE, bits);
+            }
+        }
+
+        bits.extend(body);
+    }
+
+    fn write_number(&self, n: usize, length: usize, bits: &mut Vec<Bit>) {
+        bits.extend(BitsIterator::new_with_length(n, length as u32));
+    }
+}
+
+#[aoc_generator(day16)]
+fn parse_input_day16(input: &str) -> Result<String, ParseError> {
+    let source = Source::new(16, input);
+
+    if input.is_empty() {
+        return Err(source.missing(input, "a hexadecimal transmission"));
+    }
+
+    Ok(source
+        .chars(input, |c| c.is_ascii_hexdigit().then_some(c))?
+        .into_iter()
+        .collect())
+}
+
 #[aoc(day16, part1)]
 fn day16_part1(input: &str) -> Option<usize> {
     let scanner = Scanner::new(input);
@@ -343,9 +558,7 @@ fn day16_part2(input: &str) -> Option<usize> {
 fn sum_versions(p: &Packet) -> usize {
     p.version as usize
         + match p.packet_type {
-            Operator { ref packets, op: _ } => {
-                packets.iter().fold(0, |sum, p| sum + sum_versions(p))
-            }
+            Operator { ref packets, .. } => packets.iter().fold(0, |sum, p| sum + sum_versions(p)),
             _ => 0,
         }
 }
@@ -353,6 +566,7 @@ fn sum_versions(p: &Packet) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Rng;
 
     #[test]
     fn test_parser_iterator() {
@@ -387,6 +601,93 @@ mod tests {
         assert_eq!(day16_part1(input), Some(31));
     }
 
+    fn decode(hex: &str) -> Packet {
+        Parser::new(Scanner::new(hex)).parse()
+    }
+
+    #[test]
+    fn test_encode() {
+        let literal = PacketBuilder::literal(2021).version(6).build();
+        assert_eq!(Encoder {}.encode(&literal), "D2FE28");
+        assert_eq!(literal, decode("D2FE28"));
+
+        let bits = PacketBuilder::operator(Op::LessThan)
+            .version(1)
+            .length_type(LengthType::Bits)
+            .packet(PacketBuilder::literal(10).version(6).build())
+            .packet(PacketBuilder::literal(20).version(2).build())
+            .build();
+        assert_eq!(Encoder {}.encode(&bits), "38006F45291200");
+
+        let count = PacketBuilder::operator(Op::Maximum)
+            .version(7)
+            .packets([
+                PacketBuilder::literal(1).version(2).build(),
+                PacketBuilder::literal(2).version(4).build(),
+                PacketBuilder::literal(3).version(1).build(),
+            ])
+            .build();
+        assert_eq!(Encoder {}.encode(&count), "EE00D40C823060");
+    }
+
+    fn random_packet(rng: &mut Rng, depth: usize, max_literal: u64) -> Packet {
+        let version = rng.below(8) as u8;
+
+        if depth == 0 || rng.below(3) == 0 {
+            return PacketBuilder::literal(rng.below(max_literal) as usize)
+                .version(version)
+                .build();
+        }
+
+        let (op, count) = match rng.below(7) {
+            0 => (Op::Sum, 1 + rng.below(3)),
+            1 => (Op::Product, 1 + rng.below(3)),
+            2 => (Op::Minimum, 1 + rng.below(3)),
+            3 => (Op::Maximum, 1 + rng.below(3)),
+            4 => (Op::GreaterThan, 2),
+            5 => (Op::LessThan, 2),
+            _ => (Op::EqualTo, 2),
+        };
+
+        let length_type = if rng.below(2) == 0 {
+            LengthType::Bits
+        } else {
+            LengthType::Count
+        };
+
+        PacketBuilder::operator(op)
+            .version(version)
+            .length_type(length_type)
+            .packets((0..count).map(|_| random_packet(rng, depth - 1, max_literal)))
+            .build()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Rng::new(16);
+
+        for _ in 0..500 {
+            let p = random_packet(&mut rng, 4, 1 << 48);
+
+            assert_eq!(decode(&Encoder {}.encode(&p)), p);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_evaluate() {
+        let mut rng = Rng::new(2021);
+        let interpreter = Interpreter {};
+
+        for _ in 0..500 {
+            let p = random_packet(&mut rng, 3, 4);
+
+            assert_eq!(
+                interpreter.evaluate(&decode(&Encoder {}.encode(&p))),
+                interpreter.evaluate(&p)
+            );
+        }
+    }
+
     #[test]
     fn test_part2() {
         let input = "9C0141080250320F1802104A08";
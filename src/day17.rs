@@ -1,3 +1,4 @@
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use regex::Regex;
 
@@ -43,24 +44,25 @@ impl TargetArea {
 }
 
 #[aoc_generator(day17)]
-fn parse_input_day17(input: &str) -> TargetArea {
+fn parse_input_day17(input: &str) -> Result<TargetArea, ParseError> {
+    let source = Source::new(17, input);
     let regex = Regex::new(r"target area: x=(\d+)..(\d+), y=(-?\d+)..(-?\d+)").unwrap();
 
-    let captures = regex.captures(input).unwrap();
-    let mut captures = captures.iter();
+    let captures = regex
+        .captures(input)
+        .ok_or_else(|| source.unexpected(input))?;
+    let number = |i| source.number(captures.get(i).unwrap().as_str());
 
-    captures.next();
-
-    TargetArea {
+    Ok(TargetArea {
         x_range: Range {
-            start: captures.next().unwrap().unwrap().as_str().parse().unwrap(),
-            end: captures.next().unwrap().unwrap().as_str().parse().unwrap(),
+            start: number(1)?,
+            end: number(2)?,
         },
         y_range: Range {
-            start: captures.next().unwrap().unwrap().as_str().parse().unwrap(),
-            end: captures.next().unwrap().unwrap().as_str().parse().unwrap(),
+            start: number(3)?,
+            end: number(4)?,
         },
-    }
+    })
 }
 
 #[aoc(day17, part1)]
@@ -140,7 +142,7 @@ mod tests {
         let input = "target area: x=34..67, y=-215..-186";
 
         assert_eq!(
-            parse_input_day17(input),
+            parse_input_day17(input).unwrap(),
             TargetArea {
                 x_range: Range { start: 34, end: 67 },
                 y_range: Range {
@@ -154,13 +156,13 @@ mod tests {
     #[test]
     fn test_part1() {
         let input = "target area: x=20..30, y=-10..-5";
-        assert_eq!(day17_part1(&parse_input_day17(input)), Some(45));
+        assert_eq!(day17_part1(&parse_input_day17(input).unwrap()), Some(45));
     }
 
     #[test]
     #[ignore]
     fn test_part2() {
         let input = "target area: x=20..30, y=-10..-5";
-        assert_eq!(day17_part2(&parse_input_day17(input)), Some(112));
+        assert_eq!(day17_part2(&parse_input_day17(input).unwrap()), Some(112));
     }
 }
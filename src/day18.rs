@@ -1,4 +1,5 @@
 use crate::day18::Number::{Pair, Regular};
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::fmt::Formatter;
 use std::ops::Add;
@@ -114,40 +115,62 @@ impl std::fmt::Debug for Number {
     }
 }
 
-impl From<&str> for Number {
-    fn from(l: &str) -> Self {
+impl Number {
+    fn parse(source: &Source, l: &str) -> Result<Self, ParseError> {
         let mut chars = l.chars();
-        Self::from_chars(&mut chars).unwrap()
+        let number = Self::from_chars(source, &mut chars)?;
+
+        match chars.as_str() {
+            "" => Ok(number),
+            rest => Err(source.unexpected(rest)),
+        }
     }
-}
 
-impl Number {
-    fn from_chars(l: &mut Chars) -> Option<Self> {
-        l.next().and_then(|c| match c {
-            '[' => {
-                let left = Self::from_chars(l);
-                let _comma = Self::from_chars(l);
-                let right = Self::from_chars(l);
-                let _closing = Self::from_chars(l);
-                Some(Pair(left.unwrap().into(), right.unwrap().into()))
+    fn from_chars(source: &Source, l: &mut Chars) -> Result<Self, ParseError> {
+        let rest = l.as_str();
+
+        match l.next() {
+            Some('[') => {
+                let left = Self::from_chars(source, l)?;
+                Self::expect(source, l, ',', "a comma")?;
+                let right = Self::from_chars(source, l)?;
+                Self::expect(source, l, ']', "a closing bracket")?;
+                Ok(Pair(left.into(), right.into()))
             }
-            d if c.is_digit(10) => Some(Regular(d.to_digit(10).unwrap() as usize)),
-            ']' | ',' => None,
-            _ => unreachable!(),
-        })
+            Some(d) if d.is_ascii_digit() => Ok(Regular(d.to_digit(10).unwrap() as usize)),
+            Some(c) => Err(source.unexpected(&rest[..c.len_utf8()])),
+            None => Err(source.missing(rest, "a number")),
+        }
+    }
+
+    fn expect(
+        source: &Source,
+        l: &mut Chars,
+        token: char,
+        expected: &'static str,
+    ) -> Result<(), ParseError> {
+        let rest = l.as_str();
+
+        match l.next() {
+            Some(c) if c == token => Ok(()),
+            Some(c) => Err(source.unexpected(&rest[..c.len_utf8()])),
+            None => Err(source.missing(rest, expected)),
+        }
     }
 }
 
 #[aoc_generator(day18)]
-fn parse_input_day18(input: &str) -> Vec<Number> {
-    input.lines().map(Number::from).collect()
+fn parse_input_day18(input: &str) -> Result<Vec<Number>, ParseError> {
+    let source = Source::new(18, input);
+
+    input.lines().map(|l| Number::parse(&source, l)).collect()
 }
 
 #[aoc(day18, part1)]
 fn day18_part1(input: &[Number]) -> Option<usize> {
     let mut result = input[0].clone();
 
-    for number in input.iter().cloned().skip(1) {
+    for number in input.iter().skip(1).cloned() {
         result = result + number;
     }
 
@@ -183,7 +206,7 @@ mod tests {
 [[9,3],[[9,9],[6,[4,9]]]]
 [[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
 [[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
-        assert_eq!(day18_part1(&parse_input_day18(input)), Some(4140));
+        assert_eq!(day18_part1(&parse_input_day18(input).unwrap()), Some(4140));
     }
 
     #[test]
@@ -198,6 +221,6 @@ mod tests {
 [[9,3],[[9,9],[6,[4,9]]]]
 [[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
 [[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
-        assert_eq!(day18_part2(&parse_input_day18(input)), Some(3993));
+        assert_eq!(day18_part2(&parse_input_day18(input).unwrap()), Some(3993));
     }
 }
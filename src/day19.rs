@@ -0,0 +1,382 @@
+use crate::parse::{ParseError, Source};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Sub};
+
+const MIN_OVERLAP: usize = 12;
+
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash)]
+struct Point {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl Point {
+    fn manhattan(&self, other: &Point) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs() + (self.z - other.z).abs()
+    }
+
+    fn rotate(&self, rotation: &Rotation) -> Point {
+        let v = [self.x, self.y, self.z];
+        let row = |r: [i32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+
+        Point {
+            x: row(rotation[0]),
+            y: row(rotation[1]),
+            z: row(rotation[2]),
+        }
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Point {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Point {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+type Rotation = [[i32; 3]; 3];
+
+/// The 24 orientations a scanner can face: every signed permutation matrix with a
+/// determinant of 1.
+fn rotations() -> Vec<Rotation> {
+    let determinant = |m: &Rotation| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+
+    (0..3)
+        .permutations(3)
+        .cartesian_product((0..3).map(|_| [1, -1]).multi_cartesian_product())
+        .map(|(axes, signs)| {
+            let mut m = [[0; 3]; 3];
+            for row in 0..3 {
+                m[row][axes[row]] = signs[row];
+            }
+            m
+        })
+        .filter(|m| determinant(m) == 1)
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct Scanner {
+    beacons: Vec<Point>,
+}
+
+/// The beacons and scanners of a report, all relative to the first scanner.
+struct Map {
+    beacons: HashSet<Point>,
+    scanners: Vec<Point>,
+}
+
+impl Map {
+    fn assemble(scanners: &[Scanner]) -> Option<Self> {
+        let rotations = rotations();
+
+        let mut beacons: HashSet<Point> = scanners.first()?.beacons.iter().cloned().collect();
+        let mut positions = vec![None; scanners.len()];
+        positions[0] = Some(Point::default());
+
+        // Scanners aligned last round, only they can bring in new overlaps.
+        let mut frontier = vec![scanners[0].beacons.clone()];
+
+        while let Some(known) = frontier.pop() {
+            for (i, scanner) in scanners.iter().enumerate() {
+                if positions[i].is_some() {
+                    continue;
+                }
+
+                if let Some((position, aligned)) = Self::align(&known, scanner, &rotations) {
+                    positions[i] = Some(position);
+                    beacons.extend(aligned.iter().cloned());
+                    frontier.push(aligned);
+                }
+            }
+        }
+
+        Some(Map {
+            beacons,
+            scanners: positions.into_iter().collect::<Option<_>>()?,
+        })
+    }
+
+    /// Finds the orientation and position that make `scanner` share at least
+    /// [`MIN_OVERLAP`] beacons with `known`, returning its position and beacons moved
+    /// into the frame of `known`.
+    fn align(
+        known: &[Point],
+        scanner: &Scanner,
+        rotations: &[Rotation],
+    ) -> Option<(Point, Vec<Point>)> {
+        for rotation in rotations {
+            let rotated = scanner
+                .beacons
+                .iter()
+                .map(|b| b.rotate(rotation))
+                .collect_vec();
+
+            let mut offsets = HashMap::<Point, usize>::new();
+            for a in known {
+                for b in rotated.iter() {
+                    *offsets.entry(*a - *b).or_default() += 1;
+                }
+            }
+
+            if let Some((&offset, _)) = offsets.iter().find(|(_, &n)| n >= MIN_OVERLAP) {
+                return Some((offset, rotated.into_iter().map(|b| b + offset).collect()));
+            }
+        }
+
+        None
+    }
+}
+
+// Day 19 isn't registered with aoc-runner yet: `aoc_main!` embeds the input of every
+// registered day, and there's no puzzle input for it in `input/2021`.
+pub fn parse_input_day19(input: &str) -> Result<Vec<Scanner>, ParseError> {
+    let source = Source::new(19, input);
+
+    input
+        .split("\n\n")
+        .map(|report| {
+            let mut lines = report.lines();
+
+            let header = source.next(&mut lines, report, "a scanner header")?;
+            if !header.starts_with("--- scanner ") {
+                return Err(source.unexpected(header));
+            }
+
+            let beacons = lines
+                .map(|l| {
+                    let mut coordinates = l.split(',');
+                    let mut next = |after| {
+                        source
+                            .next(&mut coordinates, after, "a coordinate")
+                            .and_then(|c| source.number(c))
+                    };
+
+                    Ok(Point {
+                        x: next(l)?,
+                        y: next(l)?,
+                        z: next(l)?,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(Scanner { beacons })
+        })
+        .collect()
+}
+
+pub fn day19_part1(input: &[Scanner]) -> Option<usize> {
+    Map::assemble(input).map(|map| map.beacons.len())
+}
+
+pub fn day19_part2(input: &[Scanner]) -> Option<i32> {
+    let map = Map::assemble(input)?;
+
+    map.scanners
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| a.manhattan(b))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "--- scanner 0 ---
+404,-588,-901
+528,-643,409
+-838,591,734
+390,-675,-793
+-537,-823,-458
+-485,-357,347
+-345,-311,381
+-661,-816,-575
+-876,649,763
+-618,-824,-621
+553,345,-567
+474,580,667
+-447,-329,318
+-584,868,-557
+544,-627,-890
+564,392,-477
+455,729,728
+-892,524,684
+-689,845,-530
+423,-701,434
+7,-33,-71
+630,319,-379
+443,580,662
+-789,900,-551
+459,-707,401
+
+--- scanner 1 ---
+686,422,578
+605,423,415
+515,917,-361
+-336,658,858
+95,138,22
+-476,619,847
+-340,-569,-846
+567,-361,727
+-460,603,-452
+669,-402,600
+729,430,532
+-500,-761,534
+-322,571,750
+-466,-666,-811
+-429,-592,574
+-355,545,-477
+703,-491,-529
+-328,-685,520
+413,935,-424
+-391,539,-444
+586,-435,557
+-364,-763,-893
+807,-499,-711
+755,-354,-619
+553,889,-390
+
+--- scanner 2 ---
+649,640,665
+682,-795,504
+-784,533,-524
+-644,584,-595
+-588,-843,648
+-30,6,44
+-674,560,763
+500,723,-460
+609,671,-379
+-555,-800,653
+-675,-892,-343
+697,-426,-610
+578,704,681
+493,664,-388
+-671,-858,530
+-667,343,800
+571,-461,-707
+-138,-166,112
+-889,563,-600
+646,-828,498
+640,759,510
+-630,509,768
+-681,-892,-333
+673,-379,-804
+-742,-814,-386
+577,-820,562
+
+--- scanner 3 ---
+-589,542,597
+605,-692,669
+-500,565,-823
+-660,373,557
+-458,-679,-417
+-488,449,543
+-626,468,-788
+338,-750,-386
+528,-832,-391
+562,-778,733
+-938,-730,414
+543,643,-506
+-524,371,-870
+407,773,750
+-104,29,83
+378,-903,-323
+-778,-728,485
+426,699,580
+-438,-605,-362
+-469,-447,-387
+509,732,623
+647,635,-688
+-868,-804,481
+614,-800,639
+595,780,-596
+
+--- scanner 4 ---
+727,592,562
+-293,-554,779
+441,611,-461
+-714,465,-776
+-743,427,-804
+-660,-479,-426
+832,-632,460
+927,-485,-438
+408,393,-506
+466,436,-512
+110,16,151
+-258,-428,682
+-393,719,612
+-211,-452,876
+808,-476,-593
+-575,615,604
+-485,667,467
+-680,325,-822
+-627,-443,-432
+872,-547,-609
+833,512,582
+807,604,487
+839,-516,451
+891,-625,532
+-652,-548,-490
+30,-46,-14";
+
+    #[test]
+    fn test_rotations() {
+        assert_eq!(rotations().into_iter().unique().count(), 24);
+    }
+
+    #[test]
+    fn test_scanner_positions() {
+        let map = Map::assemble(&parse_input_day19(INPUT).unwrap()).unwrap();
+
+        assert_eq!(
+            map.scanners[1],
+            Point {
+                x: 68,
+                y: -1246,
+                z: -43
+            }
+        );
+        assert_eq!(
+            map.scanners[4],
+            Point {
+                x: -20,
+                y: -1133,
+                z: 1061
+            }
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(day19_part1(&parse_input_day19(INPUT).unwrap()), Some(79));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(day19_part2(&parse_input_day19(INPUT).unwrap()), Some(3621));
+    }
+}
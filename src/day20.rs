@@ -1,188 +1,140 @@
-use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
+use crate::automaton::{Automaton, Neighbourhood, Rule, Update};
+use crate::cycle;
+use crate::grid::Grid;
+use crate::parse::{ParseError, Source};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
-use std::num::ParseIntError;
-use std::ops::{Index, IndexMut};
-use crate::day20::Pixel::Dark;
+use std::fmt::{Debug, Formatter};
 
-#[derive(Eq, PartialEq, Copy, Clone)]
+#[derive(Eq, PartialEq, Copy, Clone, Default, Hash)]
 enum Pixel {
+    #[default]
     Dark,
     Light,
 }
 
 impl Debug for Pixel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", match self {
-            Pixel::Dark => '.',
-            Pixel::Light => '#'
-        })
-    }
-}
-
-impl Default for Pixel {
-    fn default() -> Self {
-        Pixel::Dark
+        write!(
+            f,
+            "{}",
+            match self {
+                Pixel::Dark => '.',
+                Pixel::Light => '#',
+            }
+        )
     }
 }
 
-
-impl From<char> for Pixel {
-    fn from(c: char) -> Self {
+impl Pixel {
+    fn from_char(c: char) -> Option<Self> {
         match c {
-            '#' => Pixel::Light,
-            '.' => Pixel::Dark,
-            _ => unreachable!()
+            '#' => Some(Pixel::Light),
+            '.' => Some(Pixel::Dark),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-struct Point {
-    x: isize,
-    y: isize,
-}
-
-#[derive(Clone)]
-struct Board<T: Clone + Copy> {
-    inner: Vec<Vec<T>>,
-}
-
-impl<T: Debug + Copy + Clone + Default> Debug for Board<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for x in 0..self.inner.len() {
-            for y in 0..self.inner[0].len() {
-                write!(f, "{:?}", &self.index(Point { x: x as isize, y: y as isize }))?
-            }
-            write!(f, "\n")?
-        }
+/// How many enhancements are kept while waiting for the image to repeat itself.
+const ENHANCEMENT_LIMIT: usize = 100;
 
-        Ok(())
-    }
+/// A finite picture floating on an infinite background of identical pixels.
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct Image {
+    pixels: Automaton<Pixel>,
 }
 
-impl<T: Copy + Default> Board<T> {
-    fn new(mat: Vec<Vec<T>>) -> Self {
-        Self { inner: mat }
-    }
+impl Image {
+    /// Each pixel becomes the entry of `algorithm` indexed by the 3x3 window around it,
+    /// read as a binary number with light pixels as ones.
+    fn rule(algorithm: &[Pixel]) -> Rule<Pixel> {
+        let algorithm = algorithm.to_vec();
 
-    fn convolution(&self, index: Point) -> Vec<Point> {
-        vec![
-            Point { x: index.x - 1, y: index.y - 1 },
-            Point { x: index.x, y: index.y - 1 },
-            Point { x: index.x + 1, y: index.y - 1 },
-            Point { x: index.x - 1, y: index.y },
-            Point { x: index.x, y: index.y },
-            Point { x: index.x + 1, y: index.y },
-            Point { x: index.x - 1, y: index.y + 1 },
-            Point { x: index.x, y: index.y + 1 },
-            Point { x: index.x + 1, y: index.y + 1 },
-        ]
+        Rule {
+            neighbourhood: Neighbourhood::Window,
+            update: Update::Synchronous(Box::new(move |_, window| {
+                algorithm[window
+                    .iter()
+                    .fold(0, |index, &p| index << 1 | (p == Pixel::Light) as usize)]
+            })),
+        }
     }
-}
-
-impl<T: Copy> Index<Point> for Board<T> {
-    type Output = T;
 
-    fn index(&self, index: Point) -> &Self::Output {
-        if index.x < 0 || index.y < 0 || index.x >= self.inner.len() as isize || index.y >= self.inner.len() as isize {
-            return &self[Point{ x: 0, y: 0 }]
+    /// The same picture without the background around it, so images that only moved
+    /// compare equal.
+    fn trimmed(&self) -> Image {
+        Image {
+            pixels: self.pixels.trimmed(),
         }
-
-        &self.inner[index.y as usize][index.x as usize]
     }
-}
 
-impl<T: Copy> IndexMut<Point> for Board<T> {
-    fn index_mut(&mut self, index: Point) -> &mut Self::Output {
-        if index.x < 0 || index.y < 0 || index.x >= self.inner.len() as isize || index.y >= self.inner.len() as isize {
-            return &mut self[Point{ x: 0, y: 0 }]
+    /// The number of lit pixels, `None` when the background is lit too.
+    fn count_lit(&self) -> Option<usize> {
+        match self.pixels.background {
+            Some(Pixel::Light) => None,
+            _ => Some(
+                self.pixels
+                    .cells
+                    .cells()
+                    .iter()
+                    .filter(|&v| v == &Pixel::Light)
+                    .count(),
+            ),
         }
-
-        self.inner
-            .get_mut(index.y as usize)
-            .unwrap()
-            .get_mut(index.x as usize)
-            .unwrap()
     }
 }
 
 struct ParsedInput {
     algorithm: Vec<Pixel>,
-    image: Board<Pixel>,
+    image: Image,
 }
 
-fn parse_input_day20(input: &str) -> Option<ParsedInput> {
-    let mut parts = input.split("\n\n");
-
-    let algorithm = parts.next().unwrap().chars().map(Pixel::from).collect();
-
-    let mut map: Vec<Vec<Pixel>> = parts.next().unwrap().lines().map(|row| {
-        let mut pad_left = vec![Dark; 50];
-
-        pad_left.extend(row.chars().map(Pixel::from).chain(vec![Dark; 50]));
-
-        pad_left
-    }).collect();
+#[aoc_generator(day20)]
+fn parse_input_day20(input: &str) -> Result<ParsedInput, ParseError> {
+    let source = Source::new(20, input);
 
-    let len = map[0].len();
+    let (algorithm, image) = source.split_once(input, "\n\n")?;
 
-    for _ in 0..50 {
-        map.insert(0, vec![Dark; len]);
-        map.push(vec![Dark; len]);
+    if algorithm.len() != 512 {
+        return Err(source.missing(algorithm, "an algorithm of 512 pixels"));
     }
 
-    Some(ParsedInput {
-        algorithm,
-        image: Board::new(map),
+    Ok(ParsedInput {
+        algorithm: source.chars(algorithm, Pixel::from_char)?,
+        image: Image {
+            pixels: Automaton::unbounded(
+                Grid::parse_with(&source, image, Pixel::from_char)?,
+                Pixel::Dark,
+            ),
+        },
     })
 }
 
 #[aoc(day20, part1)]
-fn day20_part1(input: &str) -> Option<usize> {
-    enhance(input, 2)
+fn day20_part1(input: &ParsedInput) -> Option<usize> {
+    enhance(input, 2).count_lit()
 }
 
 #[aoc(day20, part2)]
-fn day20_part2(input: &str) -> Option<usize> {
-    enhance(input, 50)
+fn day20_part2(input: &ParsedInput) -> Option<usize> {
+    enhance(input, 50).count_lit()
 }
 
+/// The image after `times` enhancements, skipping ahead once it repeats itself within
+/// [`ENHANCEMENT_LIMIT`] steps.
+fn enhance(input: &ParsedInput, times: u64) -> Image {
+    let rule = Image::rule(&input.algorithm);
 
-fn enhance(input: &str, times: usize) -> Option<usize> {
-    let ParsedInput { algorithm, mut image } = parse_input_day20(input).unwrap();
-
-    for _ in 0..times {
-        let mut new_image = image.clone();
-
-        for (x, row) in image.inner.iter().enumerate() {
-            for (y, p) in row.iter().enumerate() {
-                let p = Point { x:x as isize, y: y as isize };
-
-                let binary = image.convolution(p).iter().map(|p| {
-                    match image[*p] {
-                        Pixel::Light => '1',
-                        Pixel::Dark => {
-                            match new_image[*p] {
-                                Pixel::Light => {}
-                                Pixel::Dark => { new_image[*p] = Pixel::Dark }
-                            };
-                            '0'
-                        }
-                    }
-                }).join("");
-
-                new_image[p] = algorithm[usize::from_str_radix(binary.as_str(), 2).unwrap()]
-            }
-        }
-
-        image = new_image;
-    }
-
-    let sum = image.inner.iter().fold(0, |cum, row| cum + row.iter().filter(|&v| v == &Pixel::Light).count());
-
-    Some(sum)
+    cycle::state_after(
+        input.image.trimmed(),
+        |image| {
+            let mut image = image.clone();
+            image.pixels.step(&rule);
+            image.trimmed()
+        },
+        times,
+        ENHANCEMENT_LIMIT,
+    )
 }
 
 #[cfg(test)]
@@ -198,7 +150,7 @@ mod tests {
 ##..#
 ..#..
 ..###";
-        assert_eq!(day20_part1(input), Some(35));
+        assert_eq!(day20_part1(&parse_input_day20(input).unwrap()), Some(35));
     }
 
     #[test]
@@ -210,6 +162,47 @@ mod tests {
 ##..#
 ..#..
 ..###";
-        assert_eq!(day20_part2(input), Some(3351));
+        assert_eq!(day20_part2(&parse_input_day20(input).unwrap()), Some(3351));
+    }
+
+    #[test]
+    fn test_flashing_background() {
+        // Inverts the centre pixel, so the infinite background flips on every step.
+        let algorithm = (0..512)
+            .map(|i| if i & 0b10000 == 0 { '#' } else { '.' })
+            .collect::<String>();
+        let input = format!("{}\n\n#..\n.#.\n..#", algorithm);
+        let input = parse_input_day20(&input).unwrap();
+
+        let lit = |times| enhance(&input, times).count_lit();
+        assert_eq!(lit(1), None);
+        assert_eq!(lit(2), Some(3));
+        assert_eq!(lit(3), None);
+        assert_eq!(lit(50), Some(3));
+        assert_eq!(lit(1_000_000), Some(3));
+        assert_eq!(lit(1_000_001), None);
+    }
+
+    #[test]
+    fn test_repeating_image() {
+        // Keeps the pixel up and to the left of every lit one, sliding the picture away.
+        let algorithm = (0..512)
+            .map(|i| if i & 1 == 1 { '#' } else { '.' })
+            .collect::<String>();
+        let input = format!("{}\n\n#.#\n.#.", algorithm);
+        let input = parse_input_day20(&input).unwrap();
+
+        assert_eq!(enhance(&input, 1_000_000).count_lit(), Some(3));
+
+        // The example keeps growing, so it never repeats and gets simulated all the way.
+        let input = "..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
+
+#..#.
+#....
+##..#
+..#..
+..###";
+        let input = parse_input_day20(input).unwrap();
+        assert_eq!(enhance(&input, 101).count_lit(), Some(12969));
     }
 }
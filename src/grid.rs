@@ -0,0 +1,318 @@
+use crate::parse::{ParseError, Source};
+use std::ops::{Index, IndexMut};
+
+/// A cell position inside a [`Grid`], `x` being the column and `y` the row.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash, Ord, PartialOrd)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Point {
+    pub fn new(x: usize, y: usize) -> Self {
+        Point { x, y }
+    }
+
+    /// Moves the point by the given deltas, `None` if it would leave the first quadrant.
+    pub fn offset(&self, dx: isize, dy: isize) -> Option<Point> {
+        Some(Point {
+            x: self.x.checked_add_signed(dx)?,
+            y: self.y.checked_add_signed(dy)?,
+        })
+    }
+}
+
+/// Offsets of the 4-neighbourhood: right, down, left, up.
+pub const ORTHOGONAL: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Offsets of the 8-neighbourhood, orthogonal ones first.
+pub const SURROUNDING: [(isize, isize); 8] = [
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+/// A rectangular 2D map stored row by row in a flat `Vec<T>`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Self {
+        assert_eq!(width * height, cells.len(), "grid size doesn't match cells");
+
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Point) -> T) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Point { x, y }))
+            .map(&mut f)
+            .collect();
+
+        Grid::new(width, height, cells)
+    }
+
+    /// Builds a grid out of its rows, panics if they are not all the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+
+        assert!(rows.iter().all(|r| r.len() == width), "ragged grid rows");
+
+        Grid::new(width, height, rows.into_iter().flatten().collect())
+    }
+
+    /// Parses one row per line of `text`, a slice of `source`, mapping every character
+    /// through `f`.
+    pub fn parse_with(
+        source: &Source,
+        text: &str,
+        mut f: impl FnMut(char) -> Option<T>,
+    ) -> Result<Self, ParseError> {
+        let mut rows: Vec<Vec<T>> = Vec::new();
+
+        for line in text.lines() {
+            let row = source.chars(line, &mut f)?;
+
+            if let Some(width) = rows.first().map(Vec::len) {
+                if row.len() < width {
+                    return Err(source.missing(line, "more cells"));
+                }
+
+                if let Some((i, _)) = line.char_indices().nth(width) {
+                    return Err(source.unexpected(&line[i..]));
+                }
+            }
+
+            rows.push(row);
+        }
+
+        Ok(Grid::from_rows(rows))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        p.x < self.width && p.y < self.height
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        if self.contains(p) {
+            self.cells.get(self.index_of(p))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        if self.contains(p) {
+            let i = self.index_of(p);
+            self.cells.get_mut(i)
+        } else {
+            None
+        }
+    }
+
+    pub fn index_of(&self, p: Point) -> usize {
+        p.y * self.width + p.x
+    }
+
+    pub fn point_of(&self, i: usize) -> Point {
+        Point {
+            x: i % self.width,
+            y: i / self.width,
+        }
+    }
+
+    /// Moves `p` by the given deltas, `None` if the result falls outside the grid.
+    pub fn offset(&self, p: Point, dx: isize, dy: isize) -> Option<Point> {
+        p.offset(dx, dy).filter(|&q| self.contains(q))
+    }
+
+    /// The in-bounds points up, down, left and right of `p`.
+    pub fn neighbours4(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        ORTHOGONAL
+            .iter()
+            .filter_map(move |&(dx, dy)| self.offset(p, dx, dy))
+    }
+
+    /// The in-bounds points around `p`, diagonals included.
+    pub fn neighbours8(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        SURROUNDING
+            .iter()
+            .filter_map(move |&(dx, dy)| self.offset(p, dx, dy))
+    }
+
+    /// Every point of the grid in row-major order.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width;
+        (0..self.cells.len()).map(move |i| Point {
+            x: i % width,
+            y: i / width,
+        })
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.points().zip(self.cells.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Point, &mut T)> {
+        self.points().zip(self.cells.iter_mut())
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        self.cells.iter().skip(x).step_by(self.width.max(1))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    /// The cells of the `width`×`height` rectangle whose top left corner is `from`,
+    /// clipped to the grid.
+    pub fn region(
+        &self,
+        from: Point,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = (Point, &T)> {
+        let to_x = (from.x + width).min(self.width);
+        let to_y = (from.y + height).min(self.height);
+
+        (from.y..to_y)
+            .flat_map(move |y| (from.x..to_x).map(move |x| Point { x, y }))
+            .map(move |p| (p, &self[p]))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid::new(self.width, self.height, self.cells.iter().map(f).collect())
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Grid::new(width, height, vec![value; width * height])
+    }
+}
+
+impl<T: From<u8>> Grid<T> {
+    /// Parses a map of single decimal digits, one row per line.
+    pub fn parse_digits(source: &Source, text: &str) -> Result<Self, ParseError> {
+        Grid::parse_with(source, text, |c| c.to_digit(10).map(|d| T::from(d as u8)))
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, index: Point) -> &Self::Output {
+        self.get(index).expect("point outside of the grid")
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, index: Point) -> &mut Self::Output {
+        self.get_mut(index).expect("point outside of the grid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_digits() {
+        let input = "123\n456";
+        let grid = Grid::<usize>::parse_digits(&Source::new(0, input), input).unwrap();
+
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid[Point::new(2, 1)], 6);
+        assert_eq!(grid.get(Point::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_parse_ragged() {
+        let input = "123\n45\n6789";
+        let source = Source::new(0, input);
+
+        assert_eq!(
+            Grid::<u32>::parse_digits(&source, input)
+                .unwrap_err()
+                .location()
+                .line,
+            2
+        );
+        assert_eq!(
+            Grid::<u32>::parse_digits(&source, &input[..2])
+                .unwrap()
+                .width(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = Grid::filled(3, 3, 0);
+
+        assert_eq!(grid.neighbours4(Point::new(0, 0)).count(), 2);
+        assert_eq!(grid.neighbours4(Point::new(1, 1)).count(), 4);
+        assert_eq!(grid.neighbours8(Point::new(0, 0)).count(), 3);
+        assert_eq!(grid.neighbours8(Point::new(1, 1)).count(), 8);
+        assert_eq!(grid.neighbours8(Point::new(2, 1)).count(), 5);
+    }
+
+    #[test]
+    fn test_rows_columns_region() {
+        let input = "123\n456\n789";
+        let grid = Grid::<u32>::parse_digits(&Source::new(0, input), input).unwrap();
+
+        assert_eq!(grid.row(1), &[4, 5, 6]);
+        assert_eq!(grid.column(2).cloned().collect::<Vec<_>>(), vec![3, 6, 9]);
+        assert_eq!(
+            grid.region(Point::new(1, 1), 5, 5)
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>(),
+            vec![5, 6, 8, 9]
+        );
+    }
+}
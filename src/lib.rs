@@ -1,25 +1,32 @@
-#![feature(try_trait_v2)]
-
 use aoc_runner_derive::aoc_lib;
 
+pub mod automaton;
+pub mod bigint;
+pub mod cycle;
 mod day01;
 mod day02;
 mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
 mod day13;
 mod day14;
 mod day15;
-mod day16;
+pub mod day16;
 mod day17;
 mod day18;
+pub mod day19;
 mod day20;
+pub mod grid;
+pub mod ocr;
+pub mod parse;
+#[cfg(test)]
+mod rng;
 
 aoc_lib! { year = 2021 }
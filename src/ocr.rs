@@ -0,0 +1,236 @@
+use crate::grid::{Grid, Point};
+
+/// A fixed pitch bitmap font as drawn by Advent of Code puzzles.
+pub struct Font {
+    pub width: usize,
+    pub height: usize,
+    /// Blank columns between two letters.
+    pub spacing: usize,
+    pub glyphs: &'static [(char, &'static [&'static str])],
+}
+
+/// The 4x6 font, e.g. 2021 day 13 and 2016 day 8.
+pub const SMALL: Font = Font {
+    width: 4,
+    height: 6,
+    spacing: 1,
+    glyphs: &[
+        ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+        ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+        ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+        ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+        ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+        ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+        ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+        ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+        ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+        ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+        ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+        ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+        ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+        ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+        ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('Y', &["#...", "#...", ".#.#", "..#.", "..#.", "..#."]),
+        ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ],
+};
+
+/// The 6x10 font, e.g. 2018 day 10.
+pub const LARGE: Font = Font {
+    width: 6,
+    height: 10,
+    spacing: 2,
+    glyphs: &[
+        (
+            'A',
+            &[
+                "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'B',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+                "#....#", "#####.",
+            ],
+        ),
+        (
+            'C',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#....#", ".####.",
+            ],
+        ),
+        (
+            'E',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'F',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'G',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+                "#...##", ".###.#",
+            ],
+        ),
+        (
+            'H',
+            &[
+                "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'J',
+            &[
+                "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+                "#...#.", ".###..",
+            ],
+        ),
+        (
+            'K',
+            &[
+                "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+                "#...#.", "#....#",
+            ],
+        ),
+        (
+            'L',
+            &[
+                "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'N',
+            &[
+                "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+                "#...##", "#....#",
+            ],
+        ),
+        (
+            'P',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'R',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'X',
+            &[
+                "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'Z',
+            &[
+                "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+    ],
+};
+
+impl Font {
+    /// Reads the letter whose left edge is column `left`, `None` if it isn't in the font.
+    fn glyph(&self, pixels: &Grid<bool>, left: usize) -> Option<char> {
+        let lit = |x: usize, y: usize| pixels.get(Point::new(left + x, y)) == Some(&true);
+
+        self.glyphs
+            .iter()
+            .find(|(_, rows)| {
+                rows.iter().enumerate().all(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .all(|(x, c)| (c == '#') == lit(x, y))
+                })
+            })
+            .map(|(c, _)| *c)
+    }
+
+    /// Reads every letter of `pixels`, the first one starting at column 0. Letters
+    /// missing from the font come out as `?`.
+    pub fn read(&self, pixels: &Grid<bool>) -> String {
+        let pitch = self.width + self.spacing;
+
+        (0..pixels.width())
+            .step_by(pitch)
+            .map(|left| self.glyph(pixels, left).unwrap_or('?'))
+            .collect()
+    }
+}
+
+/// Reads `pixels` with whichever font is as tall as the picture.
+pub fn recognize(pixels: &Grid<bool>) -> Option<String> {
+    [SMALL, LARGE]
+        .iter()
+        .find(|font| font.height == pixels.height())
+        .map(|font| font.read(pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Source;
+
+    fn pixels(picture: &str) -> Grid<bool> {
+        Grid::parse_with(&Source::new(0, picture), picture, |c| Some(c == '#')).unwrap()
+    }
+
+    #[test]
+    fn test_small() {
+        let picture = "\
+###..####.#..#.###...##....##.####.#..#
+#..#.#....#.#..#..#.#..#....#....#.#..#
+###..###..##...#..#.#.......#...#..#..#
+#..#.#....#.#..###..#.......#..#...#..#
+#..#.#....#.#..#.#..#..#.#..#.#....#..#
+###..#....#..#.#..#..##...##..####..##.";
+
+        assert_eq!(recognize(&pixels(picture)), Some("BFKRCJZU".to_string()));
+    }
+
+    #[test]
+    fn test_large() {
+        let picture = "\
+#....#..#####...######
+#....#..#....#..#.....
+.#..#...#....#..#.....
+.#..#...#....#..#.....
+..##....#####...#####.
+..##....#.......#.....
+.#..#...#.......#.....
+.#..#...#.......#.....
+#....#..#.......#.....
+#....#..#.......#.....";
+
+        assert_eq!(recognize(&pixels(picture)), Some("XPF".to_string()));
+    }
+
+    #[test]
+    fn test_unknown() {
+        let picture = "#####\n#...#\n#...#\n#...#\n#####";
+
+        assert_eq!(recognize(&pixels(picture)), None);
+        assert_eq!(SMALL.read(&pixels(picture)), "?");
+    }
+}
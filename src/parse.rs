@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Where in a day's input a [`ParseError`] happened, lines and columns counting from 1.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Location {
+    pub day: u8,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "day {}, line {}, column {}",
+            self.day, self.line, self.column
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// `text` should have been a number.
+    InvalidNumber { location: Location, text: String },
+    /// `text` isn't allowed at this position.
+    UnexpectedToken { location: Location, text: String },
+    /// The input stopped before `expected` could be read.
+    MissingToken {
+        location: Location,
+        expected: &'static str,
+    },
+}
+
+impl ParseError {
+    pub fn location(&self) -> Location {
+        match self {
+            ParseError::InvalidNumber { location, .. }
+            | ParseError::UnexpectedToken { location, .. }
+            | ParseError::MissingToken { location, .. } => *location,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidNumber { location, text } => {
+                write!(f, "{}: invalid number {:?}", location, text)
+            }
+            ParseError::UnexpectedToken { location, text } => {
+                write!(f, "{}: unexpected {:?}", location, text)
+            }
+            ParseError::MissingToken { location, expected } => {
+                write!(f, "{}: expected {}", location, expected)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// The raw input of one day, used to turn any slice of it into a located [`ParseError`].
+pub struct Source<'a> {
+    day: u8,
+    input: &'a str,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(day: u8, input: &'a str) -> Self {
+        Source { day, input }
+    }
+
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Locates `part`, which must be a slice of the input.
+    pub fn locate(&self, part: &str) -> Location {
+        let start = self.input.as_ptr() as usize;
+        let offset = (part.as_ptr() as usize)
+            .saturating_sub(start)
+            .min(self.input.len());
+
+        let before = &self.input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Location {
+            day: self.day,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    pub fn unexpected(&self, part: &str) -> ParseError {
+        ParseError::UnexpectedToken {
+            location: self.locate(part),
+            text: part.to_string(),
+        }
+    }
+
+    /// Reports that `expected` is missing right after `after`.
+    pub fn missing(&self, after: &str, expected: &'static str) -> ParseError {
+        ParseError::MissingToken {
+            location: self.locate(&after[after.len()..]),
+            expected,
+        }
+    }
+
+    pub fn number<T: FromStr>(&self, part: &str) -> Result<T, ParseError> {
+        part.parse().map_err(|_| ParseError::InvalidNumber {
+            location: self.locate(part),
+            text: part.to_string(),
+        })
+    }
+
+    /// Pulls the next item out of `parts`, reporting `expected` as missing after `after`.
+    pub fn next<I: Iterator<Item = &'a str>>(
+        &self,
+        parts: &mut I,
+        after: &str,
+        expected: &'static str,
+    ) -> Result<&'a str, ParseError> {
+        parts.next().ok_or_else(|| self.missing(after, expected))
+    }
+
+    /// Splits `part` once around `separator`.
+    pub fn split_once(
+        &self,
+        part: &'a str,
+        separator: &str,
+    ) -> Result<(&'a str, &'a str), ParseError> {
+        part.split_once(separator)
+            .ok_or_else(|| ParseError::MissingToken {
+                location: self.locate(&part[part.len()..]),
+                expected: "a separator",
+            })
+    }
+
+    /// Maps every character of `line` through `f`, reporting the first one it rejects.
+    pub fn chars<T>(
+        &self,
+        line: &str,
+        mut f: impl FnMut(char) -> Option<T>,
+    ) -> Result<Vec<T>, ParseError> {
+        line.char_indices()
+            .map(|(i, c)| f(c).ok_or_else(|| self.unexpected(&line[i..i + c.len_utf8()])))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let input = "12\n3x4\n\n5";
+        let source = Source::new(1, input);
+
+        assert_eq!(
+            source.locate(&input[4..5]),
+            Location {
+                day: 1,
+                line: 2,
+                column: 2
+            }
+        );
+        assert_eq!(source.locate(&input[9..]).line, 4);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = "1,x";
+        let source = Source::new(6, input);
+
+        assert_eq!(
+            source.number::<u32>(&input[2..]).unwrap_err().to_string(),
+            "day 6, line 1, column 3: invalid number \"x\""
+        );
+        assert_eq!(
+            source.chars(input, |c| c.to_digit(10)).unwrap_err(),
+            ParseError::UnexpectedToken {
+                location: Location {
+                    day: 6,
+                    line: 1,
+                    column: 2
+                },
+                text: ",".to_string(),
+            }
+        );
+    }
+}